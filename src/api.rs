@@ -0,0 +1,827 @@
+// Embedding API
+//
+// Safe entry points for Rust programs that host f2 as a scripting layer.
+// Everything here goes through the same machinery as the REPL, but reports failures
+// as Rust errors instead of relying on the caller to watch the abort flag.
+
+use crate::engine::{
+    BUF_SIZE, ERR_ABORT, ERR_ABORT_Q, FALSE, FILE_MODE_R_O, TF, TMP_START, VARIABLE,
+};
+use crate::internals::builtin::BuiltInFn;
+use crate::internals::exceptions::exception_message;
+use std::fmt;
+
+/// Errors reported to the host by the embedding API
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForthError {
+    Aborted,                // abort or abort" was called, and not caught
+    Exception(i64),         // an uncaught THROW, including errors detected by the engine, with its code
+    LineTooLong(usize),     // a line of source does not fit in the text input buffer
+    FileNotFound(String),   // include_file could not open the file
+    StackUnderflow,         // pop or peek on an empty stack
+    StackOverflow,          // push would run the stack into the dictionary
+    UndefinedWord(String),  // no word with this name is in the dictionary
+    NotAVariable(String),   // the word exists but was not created by VARIABLE
+    BadImage(String),       // load_image found a file it could not use
+    WriteFailed(String),    // save_image could not write the file
+    InvalidSize(String),    // with_sizes was asked for memory sizes outside the supported range
+}
+
+impl fmt::Display for ForthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForthError::Aborted => write!(f, "execution aborted"),
+            ForthError::Exception(code) => {
+                write!(f, "{} (exception {code})", exception_message(*code))
+            }
+            ForthError::LineTooLong(len) => {
+                write!(f, "line of {len} characters is too long for the input buffer")
+            }
+            ForthError::FileNotFound(name) => write!(f, "unable to open file {name}"),
+            ForthError::StackUnderflow => write!(f, "stack underflow"),
+            ForthError::StackOverflow => write!(f, "stack overflow"),
+            ForthError::UndefinedWord(name) => write!(f, "undefined word {name}"),
+            ForthError::NotAVariable(name) => write!(f, "{name} is not a variable"),
+            ForthError::BadImage(reason) => write!(f, "unable to load image: {reason}"),
+            ForthError::WriteFailed(reason) => write!(f, "unable to write {reason}"),
+            ForthError::InvalidSize(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ForthError {}
+
+impl TF {
+    /// evaluate interprets (or compiles) Forth source, one line at a time
+    ///     Lines are loaded into the TIB exactly as if they had been typed at the prompt.
+    ///     Files included by the source are read to completion before the next line is processed.
+    ///     Processing stops at the first line that throws an uncaught exception, or when BYE is executed.
+    ///
+    pub fn evaluate(&mut self, source: &str) -> Result<(), ForthError> {
+        for line in source.lines() {
+            if self.should_exit() {
+                break;
+            }
+            if line.len() >= BUF_SIZE {
+                return Err(ForthError::LineTooLong(line.len()));
+            }
+            let depth = self.reader.len();
+            self.set_abort_flag(false);
+            self.u_load_tib(line);
+            self.f_eval();
+            self.u_check_abort(depth)?;
+            self.u_run_readers(depth)?;
+        }
+        Ok(())
+    }
+
+    /// include_file loads and interprets a file of Forth source, returning when it has been fully read
+    ///
+    pub fn include_file(&mut self, path: &str) -> Result<(), ForthError> {
+        match self.u_open_file(path, FILE_MODE_R_O) {
+            Some(handle) => {
+                let depth = self.reader.len();
+                self.reader.push(handle);
+                self.u_run_readers(depth)
+            }
+            None => Err(ForthError::FileNotFound(path.to_owned())),
+        }
+    }
+
+    /// execute runs the word with code field address cfa, as returned by find_word
+    ///
+    pub fn execute(&mut self, cfa: usize) -> Result<(), ForthError> {
+        self.set_abort_flag(false);
+        self.push(cfa as i64)?;
+        self.f_execute();
+        self.u_check_abort(self.reader.len())
+    }
+
+    /// register_builtin adds a word implemented by a Rust closure to the dictionary
+    ///     The word is installed exactly like the engine's own builtins, so it can be interpreted,
+    ///     compiled into definitions and decompiled by SEE, which shows the stack effect and doc string.
+    ///     The closure takes its arguments from the stack with pop and returns results with push;
+    ///     to report an error it should call f_abort, or u_throw with an exception code.
+    ///     cold_start must be called before any builtins are registered.
+    ///
+    pub fn register_builtin(
+        &mut self,
+        name: &str,
+        stack_effect: &str,
+        doc: &str,
+        code: Box<dyn FnMut(&mut TF)>,
+    ) {
+        let doc = format!("{name} {stack_effect} {doc}");
+        self.u_install_builtin(BuiltInFn::new_closure(name.to_owned(), code, doc));
+    }
+
+    /// push places a value on the data stack
+    ///
+    pub fn push(&mut self, value: i64) -> Result<(), ForthError> {
+        if self.stack_ptr <= self.data[self.here_ptr] as usize {
+            return Err(ForthError::StackOverflow);
+        }
+        self.stack_ptr -= 1;
+        self.data[self.stack_ptr] = value;
+        Ok(())
+    }
+
+    /// pop removes and returns the top of the data stack
+    ///
+    pub fn pop(&mut self) -> Result<i64, ForthError> {
+        let value = self.peek()?;
+        self.stack_ptr += 1;
+        Ok(value)
+    }
+
+    /// peek returns the top of the data stack without removing it
+    ///
+    pub fn peek(&self) -> Result<i64, ForthError> {
+        if self.stack_ptr < self.stack_start {
+            Ok(self.data[self.stack_ptr])
+        } else {
+            Err(ForthError::StackUnderflow)
+        }
+    }
+
+    /// depth returns the number of values on the data stack
+    ///
+    pub fn depth(&self) -> usize {
+        self.stack_start - self.stack_ptr
+    }
+
+    /// stack returns a copy of the data stack, with the top of the stack last
+    ///
+    pub fn stack(&self) -> Vec<i64> {
+        self.data[self.stack_ptr..self.stack_start].iter().rev().copied().collect()
+    }
+
+    /// find_word looks up a word by name, returning its code field address (cfa) if it is defined
+    ///
+    pub fn find_word(&self, name: &str) -> Option<usize> {
+        self.u_search(name)
+    }
+
+    /// get_variable returns the current value of a Forth variable
+    ///
+    pub fn get_variable(&self, name: &str) -> Result<i64, ForthError> {
+        let cfa = self.u_variable_cfa(name)?;
+        Ok(self.data[cfa + 1])
+    }
+
+    /// set_variable stores a new value in a Forth variable
+    ///
+    pub fn set_variable(&mut self, name: &str, value: i64) -> Result<(), ForthError> {
+        let cfa = self.u_variable_cfa(name)?;
+        self.data[cfa + 1] = value;
+        Ok(())
+    }
+
+    /// u_variable_cfa finds a word and checks that it is a variable
+    ///
+    fn u_variable_cfa(&self, name: &str) -> Result<usize, ForthError> {
+        match self.find_word(name) {
+            Some(cfa) if self.data[cfa] == VARIABLE => Ok(cfa),
+            Some(_) => Err(ForthError::NotAVariable(name.to_owned())),
+            None => Err(ForthError::UndefinedWord(name.to_owned())),
+        }
+    }
+
+    /// u_load_tib places a line in the TIB and resets the input pointers, as QUERY does
+    ///
+    fn u_load_tib(&mut self, line: &str) {
+        self.u_save_string(line, self.data[self.tib_ptr] as usize);
+        self.data[self.tib_size_ptr] = line.len() as i64;
+        self.data[self.tib_in_ptr] = 1;
+        self.data[self.tmp_ptr] = TMP_START as i64; // transient strings last until the end of the line
+    }
+
+    /// u_run_readers processes lines from any files pushed on the reader stack above depth
+    ///
+    fn u_run_readers(&mut self, depth: usize) -> Result<(), ForthError> {
+        while self.reader.len() > depth && !self.should_exit() {
+            self.set_abort_flag(false);
+            self.f_query();
+            self.f_eval();
+            self.u_check_abort(depth)?;
+        }
+        Ok(())
+    }
+
+    /// u_check_abort converts an uncaught exception into an error, closing any files opened since depth
+    ///     The stacks are cleared and compilation is abandoned, so the next evaluation starts afresh.
+    ///
+    fn u_check_abort(&mut self, depth: usize) -> Result<(), ForthError> {
+        match self.u_clear_exception(depth) {
+            FALSE => Ok(()),
+            ERR_ABORT | ERR_ABORT_Q => Err(ForthError::Aborted),
+            code => Err(ForthError::Exception(code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ForthError;
+    use crate::engine::{BREAK, DUP_BRANCH0, EXIT, LIT_PLUS, OVER_OVER, R_FROM_DROP, TF};
+    use crate::internals::image::embedded_image;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn forth() -> TF {
+        let mut forth = TF::new();
+        forth.cold_start();
+        forth.include_file("src/forth/corelib.fs").unwrap();
+        forth.evaluate("clear").unwrap();
+        forth
+    }
+
+    #[test]
+    fn evaluate_arithmetic() {
+        let mut f = forth();
+        f.evaluate("2 3 + 4 *").unwrap();
+        assert_eq!(f.pop(), Ok(20));
+        assert_eq!(f.depth(), 0);
+    }
+
+    #[test]
+    fn evaluate_definitions() {
+        let mut f = forth();
+        f.evaluate(": sq dup * ;\n7 sq").unwrap();
+        assert_eq!(f.stack(), vec![49]);
+    }
+
+    #[test]
+    fn evaluate_abort() {
+        let mut f = forth();
+        assert_eq!(f.evaluate("1 2 abort 3"), Err(ForthError::Aborted));
+        assert_eq!(f.depth(), 0);
+        assert!(f.evaluate("4").is_ok());
+    }
+
+    #[test]
+    fn stack_access() {
+        let mut f = forth();
+        f.push(5).unwrap();
+        f.push(6).unwrap();
+        assert_eq!(f.peek(), Ok(6));
+        f.evaluate("swap").unwrap();
+        assert_eq!(f.stack(), vec![6, 5]);
+        f.pop().unwrap();
+        f.pop().unwrap();
+        assert_eq!(f.pop(), Err(ForthError::StackUnderflow));
+    }
+
+    #[test]
+    fn words_and_variables() {
+        let mut f = forth();
+        f.evaluate("variable counter 10 constant ten").unwrap();
+        assert!(f.find_word("dup").is_some());
+        assert!(f.find_word("no-such-word").is_none());
+        f.set_variable("counter", 41).unwrap();
+        f.evaluate("1 counter +!").unwrap();
+        assert_eq!(f.get_variable("counter"), Ok(42));
+        assert_eq!(
+            f.get_variable("ten"),
+            Err(ForthError::NotAVariable("ten".to_owned()))
+        );
+        let cfa = f.find_word("ten").unwrap();
+        f.execute(cfa).unwrap();
+        assert_eq!(f.pop(), Ok(10));
+    }
+
+    #[test]
+    fn closure_builtins() {
+        let mut f = forth();
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        f.register_builtin(
+            "count-call",
+            "( n -- n+1 )",
+            "Counts its calls and increments n",
+            Box::new(move |forth: &mut TF| {
+                counter.set(counter.get() + 1);
+                match forth.pop() {
+                    Ok(n) => forth.push(n + 1).unwrap(),
+                    Err(_) => forth.f_abort(),
+                }
+            }),
+        );
+        f.evaluate("1 count-call : twice count-call count-call ; twice").unwrap();
+        assert_eq!(f.stack(), vec![4]);
+        assert_eq!(calls.get(), 3);
+        f.evaluate("clear").unwrap();
+        assert_eq!(f.evaluate("count-call"), Err(ForthError::Aborted));
+    }
+
+    #[test]
+    fn invalid_addresses_abort() {
+        let mut f = forth();
+        for line in ["99999 @", "1 -5 !", "65 99999 c!", "-1 c@", "99999 execute", "0 execute"] {
+            assert_eq!(f.evaluate(line), Err(ForthError::Exception(-9)), "{line}");
+            assert_eq!(f.depth(), 0);
+        }
+        f.evaluate("1 0 ' execute catch").unwrap();
+        assert_eq!(f.stack(), vec![1, 0, -9]); // execute throws, rather than carrying on
+        f.evaluate("clear").unwrap();
+        assert_eq!(f.evaluate("r>"), Err(ForthError::Exception(-6)));
+        f.evaluate("variable v 7 v ! v @").unwrap();
+        assert_eq!(f.pop(), Ok(7));
+    }
+
+    #[test]
+    fn catch_and_throw() {
+        let mut f = forth();
+        f.evaluate("' dup catch").unwrap(); // the stack is empty, so dup throws -4
+        assert_eq!(f.stack(), vec![-4]);
+        f.evaluate("clear").unwrap();
+        f.evaluate(": risky ( n -- n ) dup 0< if 99 throw then 10 * ;").unwrap();
+        f.evaluate("1 2 ' risky catch").unwrap();
+        assert_eq!(f.stack(), vec![1, 20, 0]);
+        f.evaluate("clear 1 -2 ' risky catch").unwrap();
+        assert_eq!(f.stack(), vec![1, -2, 99]); // the stack is restored, with the code on top
+        f.evaluate("clear : safe ['] risky catch 1 ; -3 safe").unwrap();
+        assert_eq!(f.stack(), vec![-3, 99, 1]); // the definition continues after catch
+        f.evaluate("clear ' abort catch 0 throw").unwrap();
+        assert_eq!(f.stack(), vec![-1]);
+        f.evaluate("clear : msg abort\" oops\" ; ' msg catch  99999 ' @ catch").unwrap();
+        assert_eq!(f.stack(), vec![-2, 99999, -9]);
+        assert_eq!(f.evaluate("5 throw"), Err(ForthError::Exception(5)));
+        assert_eq!(f.depth(), 0);
+        assert_eq!(f.evaluate("no-such-word"), Err(ForthError::Exception(-13)));
+        assert_eq!(f.evaluate("msg"), Err(ForthError::Aborted));
+    }
+
+    #[test]
+    fn aborted_definition() {
+        let mut f = forth();
+        f.evaluate("create buf 5 ,").unwrap();
+        let (here, s_here) = (f.data[f.here_ptr], f.data[f.string_ptr]);
+        assert_eq!(f.evaluate(": misspelt 1 dupp ;"), Err(ForthError::Exception(-13)));
+        assert_eq!((f.data[f.here_ptr], f.data[f.string_ptr]), (here, s_here)); // the header is gone
+        assert!(f.find_word("misspelt").is_none());
+        f.evaluate(": y 1 ; y buf @ +").unwrap();
+        assert_eq!(f.stack(), vec![6]);
+    }
+
+    #[test]
+    fn division_errors() {
+        let mut f = forth();
+        assert_eq!(f.evaluate("1 0 /"), Err(ForthError::Exception(-10)));
+        assert_eq!(f.evaluate("1 0 mod"), Err(ForthError::Exception(-10)));
+        assert_eq!(f.evaluate("-9223372036854775808 -1 /"), Err(ForthError::Exception(-11)));
+        f.evaluate("7 2 / 7 2 mod").unwrap();
+        assert_eq!(f.stack(), vec![3, 1]);
+        assert_eq!(f.evaluate("1 0 /mod"), Err(ForthError::Exception(-10)));
+        assert_eq!(f.evaluate("-9223372036854775808 -1 mod"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("-9223372036854775808 -1 /mod"), Err(ForthError::Exception(-11)));
+        // division is symmetric; fm/mod gives floored division
+        f.evaluate("7 -2 / 7 -2 mod 7 s>d -2 fm/mod").unwrap();
+        assert_eq!(f.stack(), vec![-3, 1, -1, -4]);
+    }
+
+    #[test]
+    fn images() {
+        let path = std::env::temp_dir().join(format!("f2-test-{}.img", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut f = forth();
+        f.register_builtin("host-a", "( -- n )", "", Box::new(|forth: &mut TF| forth.push(1).unwrap()));
+        f.evaluate(": sq dup * ; variable v 5 v ! : go v @ sq -3 + host-a + ;").unwrap();
+        f.evaluate(": ticked ['] host-a execute ; variable xt ' host-a xt ! create table ' host-a ,").unwrap();
+        f.evaluate(": half ( F: r -- r ) 0.5e0 f* ;").unwrap(); // float literals are kept as bits
+        f.evaluate("SATURATING overflow-mode !").unwrap(); // the mode is kept in data space
+        f.evaluate(": bump ( n -- n ) dup if 1 + then ;").unwrap(); // superinstructions
+        // the most recent word isn't closed until the next one is defined, so saving must close it
+        f.evaluate(": const create , does> @ ; 5 const five create open 1 , 2 ,").unwrap();
+        f.save_image(path).unwrap();
+
+        // a different host registers its builtins in a different order
+        let mut g = TF::new();
+        g.cold_start();
+        g.register_builtin("host-b", "( -- n )", "", Box::new(|forth: &mut TF| forth.push(100).unwrap()));
+        g.register_builtin("host-a", "( -- n )", "", Box::new(|forth: &mut TF| forth.push(2).unwrap()));
+        g.push(9).unwrap();
+        g.load_image(path).unwrap();
+        assert_eq!(g.depth(), 0);
+        g.evaluate("go").unwrap();
+        assert_eq!(g.stack(), vec![24]);
+        // so are the xts given by ' and [']
+        g.evaluate("clear ticked xt @ execute table @ execute").unwrap();
+        assert_eq!(g.stack(), vec![2, 2, 2]);
+        g.evaluate("clear open 1 + @ five 4e half 9223372036854775807 1 + 41 bump").unwrap();
+        assert_eq!(g.stack(), vec![2, 5, i64::MAX, 42]);
+        assert_eq!(g.floats, vec![2.0]);
+        g.evaluate("clear : t 3 sq abort\" x\" ;").unwrap();
+        assert_eq!(g.evaluate("t"), Err(ForthError::Aborted));
+        assert!(g.find_word("host-b").is_none());
+
+        // an image can't be loaded without the builtins it uses
+        let mut h = TF::new();
+        h.cold_start();
+        assert!(matches!(h.load_image(path), Err(ForthError::BadImage(_))));
+        std::fs::write(path, "not an image").unwrap();
+        assert!(matches!(f.load_image(path), Err(ForthError::BadImage(_))));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(f.load_image(path), Err(ForthError::FileNotFound(path.to_owned())));
+        f.evaluate("5 sq").unwrap();
+        assert_eq!(f.pop(), Ok(25));
+    }
+
+    #[test]
+    fn turnkey() {
+        let path = std::env::temp_dir().join(format!("f2-test-{}.turnkey", std::process::id()));
+        let mut f = forth();
+        f.evaluate(": main 6 7 * ;").unwrap();
+        let name = path.to_str().unwrap();
+        assert_eq!(
+            f.save_turnkey(name, "nosuch"),
+            Err(ForthError::UndefinedWord("nosuch".to_owned()))
+        );
+        f.save_turnkey(name, "main").unwrap();
+        let (image, entry) = embedded_image(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entry, "main");
+        let mut g = TF::new();
+        g.cold_start();
+        g.load_image_bytes(&image).unwrap();
+        g.execute(g.find_word(&entry).unwrap()).unwrap();
+        assert_eq!(g.pop(), Ok(42));
+    }
+
+    #[test]
+    fn memory_sizes() {
+        assert!(matches!(TF::with_sizes(100, 5000), Err(ForthError::InvalidSize(_))));
+        assert!(matches!(TF::with_sizes(10000, 1 << 30), Err(ForthError::InvalidSize(_))));
+        let mut f = TF::with_sizes(40000, 20000).unwrap();
+        f.cold_start();
+        f.include_file("src/forth/corelib.fs").unwrap();
+        f.evaluate("clear unused s-unused").unwrap();
+        let (unused, s_unused) = (f.pop().unwrap(), f.pop().unwrap());
+        assert!(unused > 15000 && s_unused > 15000);
+        for i in 0..10000 {
+            f.push(i).unwrap(); // deeper than the whole default data space allows
+        }
+        f.evaluate("+").unwrap();
+        assert_eq!(f.pop(), Ok(9998 + 9999));
+        assert_eq!(f.depth(), 9998);
+
+        // an image can be loaded into a smaller engine, as long as the dictionary fits
+        let path = std::env::temp_dir().join(format!("f2-test-{}.sizes", std::process::id()));
+        let path = path.to_str().unwrap();
+        f.evaluate(": f 1 + ;").unwrap();
+        f.save_image(path).unwrap();
+        let mut g = TF::new();
+        g.cold_start();
+        g.load_image(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        g.evaluate("41 f").unwrap();
+        assert_eq!(g.stack(), vec![42]);
+    }
+
+    #[test]
+    fn nested_execute() {
+        let mut f = forth();
+        f.evaluate(": bar 1 ; : foo ['] bar execute 2 ; : baz foo 3 ; baz").unwrap();
+        assert_eq!(f.stack(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn counted_loops() {
+        let mut f = forth();
+        f.evaluate(": squares 4 0 do i i * loop ; squares").unwrap();
+        assert_eq!(f.stack(), vec![0, 1, 4, 9]);
+        f.evaluate("clear : down 0 6 do i -3 +loop ; down").unwrap();
+        assert_eq!(f.stack(), vec![6, 3, 0]);
+        f.evaluate("clear : none 3 3 ?do i loop ; none").unwrap();
+        assert_eq!(f.stack(), Vec::<i64>::new());
+        f.evaluate(": grid 2 0 do 3 0 do j 10 * i + dup 11 = if leave then loop loop ; grid").unwrap();
+        assert_eq!(f.stack(), vec![0, 1, 2, 10, 11]);
+        f.evaluate("clear : after 5 0 do i 2 = if unloop 99 exit then loop ; after").unwrap();
+        assert_eq!(f.stack(), vec![99]);
+        assert_eq!(f.evaluate("clear : bad unloop ; bad"), Err(ForthError::Exception(-6)));
+    }
+
+    #[test]
+    fn create_does() {
+        let mut f = forth();
+        f.evaluate(": array create 0 do 0 , loop does> swap + ; 4 array a  9 2 a !  2 a @").unwrap();
+        assert_eq!(f.stack(), vec![9]);
+        f.evaluate("clear create table 10 , 20 , : second table 1 + @ ; second").unwrap();
+        assert_eq!(f.stack(), vec![20]);
+        f.evaluate("clear : const create , does> @ ; 5 const five ' five execute five").unwrap();
+        assert_eq!(f.stack(), vec![5, 5]);
+        // each word made by a defining word keeps its own data, and runs the DOES> code it was given
+        f.evaluate("clear : counter create 0 , does> dup 1 swap +! @ ; counter c1 counter c2 c1 c1 c2").unwrap();
+        assert_eq!(f.stack(), vec![1, 2, 1]);
+        f.evaluate("clear ' five >body @ create plain plain here @ -").unwrap();
+        assert_eq!(f.stack(), vec![5, 0]); // a word made by CREATE alone pushes its data field, at HERE
+        assert_eq!(
+            f.evaluate(": bad does> @ ; variable v bad"),
+            Err(ForthError::Exception(-21))
+        );
+    }
+
+    #[test]
+    fn wordlists() {
+        let mut f = forth();
+        f.evaluate("vocabulary extra  also extra definitions  : hello 42 ;  : + * ;  forth-wordlist set-current").unwrap();
+        f.evaluate("hello 3 4 +").unwrap();
+        assert_eq!(f.stack(), vec![42, 12]);
+        f.evaluate("clear previous 3 4 +").unwrap();
+        assert_eq!(f.stack(), vec![7]);
+        assert!(f.find_word("hello").is_none());
+        assert_eq!(f.evaluate("hello"), Err(ForthError::Exception(-13)));
+        f.evaluate("also extra").unwrap();
+        assert!(f.find_word("hello").is_some());
+        f.evaluate("only get-order").unwrap();
+        assert_eq!(f.stack(), vec![0, 1]);
+        assert_eq!(f.evaluate("previous"), Err(ForthError::Exception(-50)));
+        assert_eq!(f.evaluate("0 1 2 3 4 5 6 7 8 9 set-order"), Err(ForthError::Exception(-49)));
+        assert_eq!(f.evaluate("99 set-current"), Err(ForthError::Exception(-24)));
+    }
+
+    #[test]
+    fn hashed_lookup() {
+        let mut f = forth();
+        f.evaluate(": w 1 ; : w 2 ; : other 3 ;").unwrap();
+        let newest = f.find_word("w").unwrap();
+        f.evaluate("forget-last w forget w w").unwrap();
+        assert_eq!(f.stack(), vec![2, 1]);
+        assert_ne!(f.find_word("w"), Some(newest));
+        // a new word may reuse the addresses of forgotten ones
+        f.evaluate("clear forget w : x 4 ; x").unwrap();
+        assert_eq!(f.stack(), vec![4]);
+        assert!(f.find_word("w").is_none());
+        f.register_builtin("host", "( -- n )", "", Box::new(|forth: &mut TF| forth.push(5).unwrap()));
+        f.evaluate("clear host create c 6 , c @").unwrap();
+        assert_eq!(f.stack(), vec![5, 6]);
+        let image = f.image_bytes();
+        let mut g = TF::new();
+        g.cold_start();
+        g.register_builtin("host", "( -- n )", "", Box::new(|forth: &mut TF| forth.push(5).unwrap()));
+        g.evaluate(": c 7 ;").unwrap();
+        g.load_image_bytes(&image).unwrap();
+        g.evaluate("c @ x").unwrap();
+        assert_eq!(g.stack(), vec![6, 4]);
+    }
+
+    #[test]
+    fn lookup_after_bad_link() {
+        let mut f = forth();
+        f.evaluate(": a 1 ; : b 2 ;").unwrap();
+        let nfa = f.data[f.context_ptr] as usize;
+        f.data[nfa - 1] = 40; // b's back pointer skips the rest of the dictionary
+        f.evaluate("b a 1 2 +").unwrap();
+        assert_eq!(f.stack(), vec![2, 1, 3]);
+        // and after an aborted definition
+        assert_eq!(f.evaluate(": c nosuch ;"), Err(ForthError::Exception(-13)));
+        f.evaluate("clear : d 4 ; d b +").unwrap();
+        assert_eq!(f.stack(), vec![6]);
+    }
+
+    #[test]
+    fn floats() {
+        let mut f = forth();
+        f.evaluate("1.5e0 2e f*").unwrap();
+        assert_eq!(f.floats, vec![3.0]);
+        assert!(f.stack().is_empty());
+        f.evaluate(": half ( F: r -- r ) 0.5e0 f* ; half 2 s>f half f>s").unwrap();
+        assert_eq!(f.floats, vec![1.5]);
+        assert_eq!(f.stack(), vec![1]);
+        assert_eq!(f.evaluate("1.5"), Err(ForthError::Exception(-13)));
+        assert!(f.floats.is_empty()); // an uncaught exception empties the float stack
+        assert_eq!(f.evaluate("fdrop"), Err(ForthError::Exception(-45)));
+        f.evaluate("3e ' fdrop catch 1e0 ' f< catch").unwrap();
+        assert_eq!(f.stack(), vec![0, -45]);
+        assert_eq!(f.floats, vec![1.0]); // restored by catch
+        // division by zero and square roots of negatives follow IEEE 754, but f>s won't convert the results
+        f.evaluate("clear fdrop 1e 0e f/ -1e fsqrt").unwrap();
+        assert_eq!(f.floats[0], f64::INFINITY);
+        assert!(f.floats[1].is_nan());
+        assert_eq!(f.evaluate("f>s"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("1e300 1e300 f* f>s"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate(": too-deep 65 0 do 1e loop ; too-deep"), Err(ForthError::Exception(-44)));
+        // floats are stored in data space bit for bit
+        f.evaluate("variable r 0.1e0 r f! r f@ -0e r f! r f@").unwrap();
+        assert_eq!(f.floats[0], 0.1);
+        assert!(f.floats[1] == 0.0 && f.floats[1].is_sign_negative());
+    }
+
+    #[test]
+    fn double_numbers() {
+        let mut f = forth();
+        f.evaluate("170141183460469231731687303715884105727.").unwrap();
+        assert_eq!(f.stack(), vec![-1, i64::MAX]);
+        f.evaluate("clear : big -18446744073709551616. ; big").unwrap();
+        assert_eq!(f.stack(), vec![0, -1]);
+        f.evaluate("clear 4611686018427387904 4 1000 */").unwrap();
+        assert_eq!(f.stack(), vec![18446744073709551]);
+        assert_eq!(f.evaluate("1 0 0 sm/rem"), Err(ForthError::Exception(-10)));
+        assert_eq!(f.evaluate("0 1 2 fm/mod"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("0 1 1 um/mod"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("0 -9223372036854775808 -1 sm/rem"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("0 -9223372036854775808 -1 fm/mod"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("1 2 d+"), Err(ForthError::Exception(-4)));
+    }
+
+    #[test]
+    fn number_base() {
+        let mut f = forth();
+        f.evaluate("hex : h ff 10. ; decimal h 10").unwrap();
+        assert_eq!(f.stack(), vec![255, 16, 0, 10]);
+        f.evaluate("clear 36 base ! zz #35 decimal").unwrap();
+        assert_eq!(f.stack(), vec![1295, 35]);
+        assert_eq!(f.evaluate("99999999999999999999"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate(": t 1 99999999999999999999 ;"), Err(ForthError::Exception(-11)));
+        assert_eq!(f.evaluate("hex 1e0"), Ok(()));
+        assert_eq!(f.stack(), vec![0x1e0]);
+        assert!(f.floats.is_empty());
+        assert_eq!(f.evaluate("1.5e0"), Err(ForthError::Exception(-13)));
+        assert_eq!(f.evaluate("decimal 1 base ! 7"), Err(ForthError::Exception(-24)));
+    }
+
+    #[test]
+    fn pictured_output() {
+        let mut f = forth();
+        let picture = |f: &mut TF, source: &str| {
+            f.evaluate(source).unwrap();
+            let len = f.pop().unwrap() as usize;
+            let addr = f.pop().unwrap() as usize;
+            f.strings[addr..addr + len].iter().collect::<String>()
+        };
+        assert_eq!(picture(&mut f, "-9223372036854775808 s>d swap over dabs <# #s rot sign #>"), "-9223372036854775808");
+        assert_eq!(picture(&mut f, "-1 0 <# #s #>"), "18446744073709551615");
+        assert_eq!(picture(&mut f, "-1 -1 <# #s #>"), u128::MAX.to_string());
+        assert_eq!(picture(&mut f, "hex #3054 0 <# # # # # # # '$' hold #> decimal"), "$000BEE");
+        assert_eq!(picture(&mut f, "12 0 <# s\" km\" swap 1+ swap holds BL hold #s #>"), "12 km");
+        assert_eq!(f.evaluate(": overflow <# 200 0 do 'x' hold loop ; overflow"), Err(ForthError::Exception(-17)));
+        assert_eq!(picture(&mut f, "7 0 <# # 955 hold #>"), "\u{3bb}7");
+        assert_eq!(f.evaluate("<# 55296 hold"), Err(ForthError::Exception(-24)));
+        assert_eq!(f.evaluate("1 base ! 5 0 <# #s"), Err(ForthError::Exception(-24)));
+    }
+
+    #[test]
+    fn overflow_modes() {
+        let mut f = forth();
+        let max = i64::MAX;
+        f.evaluate(&format!("{max} 1 + -2 {max} *")).unwrap();
+        assert_eq!(f.stack(), vec![i64::MIN, 2]);
+        f.evaluate("clear CHECKED overflow-mode !").unwrap();
+        for source in [format!("{max} 1 +"), format!("-2 {max} - "), format!("{max} 2 *"), "-9223372036854775808 abs".to_string()] {
+            assert_eq!(f.evaluate(&source), Err(ForthError::Exception(-11)), "{source}");
+        }
+        f.evaluate(&format!("{max} 0 + -1 1 -")).unwrap();
+        assert_eq!(f.stack(), vec![max, -2]);
+        f.evaluate(&format!("clear SATURATING overflow-mode ! {max} 1 + -2 {max} * 5 3 -")).unwrap();
+        assert_eq!(f.stack(), vec![max, i64::MIN, 2]);
+        assert_eq!(f.evaluate("1 0 /"), Err(ForthError::Exception(-10)));
+        f.evaluate("clear -9223372036854775808 negate -9223372036854775808 1-").unwrap();
+        assert_eq!(f.stack(), vec![max, i64::MIN]);
+        // checked arithmetic throws from the words built on + and -, and in compiled code, and can be caught
+        f.evaluate("clear CHECKED overflow-mode !").unwrap();
+        for source in ["-9223372036854775808 negate", "9223372036854775807 1+", "-9223372036854775808 1-"] {
+            assert_eq!(f.evaluate(source), Err(ForthError::Exception(-11)), "{source}");
+        }
+        assert_eq!(f.evaluate(&format!(": inc 1 + ; {max} inc")), Err(ForthError::Exception(-11)));
+        f.evaluate(&format!("{max} ' inc catch")).unwrap();
+        assert_eq!(f.stack(), vec![max, -11]);
+    }
+
+    #[test]
+    fn nested_calls() {
+        let mut f = forth();
+        // return addresses aren't on the return stack, so >r values and loop indices pass through calls
+        f.evaluate(": inner 1+ ; : outer 10 >r 5 inner r> ; outer").unwrap();
+        assert_eq!(f.stack(), vec![6, 10]);
+        f.evaluate("clear : nest 3 0 do i inner loop ; nest").unwrap();
+        assert_eq!(f.stack(), vec![1, 2, 3]);
+        f.evaluate("clear : early 1 exit 2 ; : caller early 3 ; caller").unwrap();
+        assert_eq!(f.stack(), vec![1, 3]);
+        // runaway recursion is stopped, and can be caught. Something must follow the call, or it would be a tail call.
+        assert_eq!(f.evaluate("clear : forever recurse 1 ; forever"), Err(ForthError::Exception(-5)));
+        f.evaluate("' forever catch : after 7 ; after").unwrap();
+        assert_eq!(f.stack(), vec![-5, 7]);
+        assert!(f.calls.is_empty());
+    }
+
+    #[test]
+    fn optimizer() {
+        let mut f = forth();
+        f.evaluate(": inc 1 + ;").unwrap();
+        let cfa = f.find_word("inc").unwrap();
+        assert_eq!(f.data[cfa + 1..cfa + 4], [LIT_PLUS, 1, EXIT]);
+        // a branch lands on the +, so the literal before it can't be fused
+        f.evaluate(": pick5 ( a b f -- n ) if drop 5 then + ; 1 2 -1 pick5 1 2 0 pick5").unwrap();
+        assert_eq!(f.stack(), vec![6, 3]);
+        f.evaluate("clear : sign? ( n -- n f ) dup if 1 else 0 then ; 7 sign? 0 sign?").unwrap();
+        assert_eq!(f.stack(), vec![7, 1, 0, 0]);
+        f.evaluate("clear : 2dup-sum ( a b -- a b n ) over over + ; : rr ( n -- ) >r r> drop ; 3 4 2dup-sum 9 rr").unwrap();
+        assert_eq!(f.stack(), vec![3, 4, 7]);
+        // superinstructions behave like the words they replace
+        f.evaluate("clear CHECKED overflow-mode !").unwrap();
+        assert_eq!(f.evaluate("9223372036854775807 inc"), Err(ForthError::Exception(-11)));
+        f.evaluate("WRAPPING overflow-mode !").unwrap();
+        assert_eq!(f.evaluate(": test-empty dup if 1 then ; test-empty"), Err(ForthError::Exception(-4)));
+        assert_eq!(f.evaluate(": drop-r r> drop ; drop-r"), Err(ForthError::Exception(-6)));
+        // each pair is replaced by one opcode, and the definition closes up behind it
+        let length = |f: &TF, name: &str| f.data[f.find_word(name).unwrap() + 1..].iter().position(|&c| c == EXIT);
+        assert_eq!(f.data[f.find_word("2dup-sum").unwrap() + 1], OVER_OVER);
+        assert_eq!(length(&f, "2dup-sum"), Some(2));
+        assert_eq!(f.data[f.find_word("rr").unwrap() + 2], R_FROM_DROP);
+        assert_eq!(f.data[f.find_word("sign?").unwrap() + 1], DUP_BRANCH0);
+        // data compiled with , isn't mistaken for a call, so it is left as it was
+        f.evaluate(": inline exit [ 1000 , ] ;").unwrap();
+        assert_eq!(f.data[f.find_word("inline").unwrap() + 1..][..3], [BREAK, 1000, EXIT]);
+    }
+
+    #[test]
+    fn tail_calls() {
+        let mut f = forth();
+        // deeper than MAX_CALL_DEPTH, which is only possible if the recursion doesn't nest
+        f.evaluate(": countdown ( n -- 0 ) dup if 1 - recurse then ; 100000 countdown").unwrap();
+        f.evaluate(": down2 ( n -- 0 ) dup 0= if exit then 1 - recurse ; 100000 down2").unwrap();
+        f.evaluate(": sum ( acc n -- acc ) dup if tuck + swap 1 - recurse else drop then ; 0 100000 sum").unwrap();
+        assert_eq!(f.stack(), vec![0, 0, 5000050000]);
+        // a tail call to a variable or constant returns to the caller's caller
+        f.evaluate("clear : the-base base ; : check the-base @ 1 ; check").unwrap();
+        assert_eq!(f.stack(), vec![10, 1]);
+        // calls that aren't in tail position still nest
+        assert_eq!(f.evaluate(": deep 1 - dup if recurse then 1 + ; 100000 deep"), Err(ForthError::Exception(-5)));
+    }
+
+    #[test]
+    fn memory_words() {
+        let mut f = forth();
+        f.evaluate("create buf 8 chars allot  buf 8 'x' fill  0 buf 3 + c!  buf 2 + c@ buf 3 + c@").unwrap();
+        assert_eq!(f.stack(), vec!['x' as i64, 0]);
+        // move copies whole cells within data space, and characters between the spaces
+        f.evaluate("clear -7 buf !  buf buf 4 cells + 1 move  buf 4 + @").unwrap();
+        assert_eq!(f.stack(), vec![-7]);
+        f.evaluate("clear s\" hello\" drop 1+ buf 5 move  buf 4 + c@  buf tmp @ 1+ 2 move tmp @ 1+ c@").unwrap();
+        assert_eq!(f.stack(), vec!['o' as i64, 'h' as i64]);
+        f.evaluate("clear buf 8 erase buf 7 + c@  here @ 'A' c, here @ swap - 3 cells cell+").unwrap();
+        assert_eq!(f.stack(), vec![0, 1, 4]);
+        // the dictionary can't grow into the stack, or be given back below the newest word
+        assert_eq!(f.evaluate("clear unused 1+ allot"), Err(ForthError::Exception(-8)));
+        f.evaluate("unused allot unused").unwrap();
+        assert_eq!(f.stack(), vec![0]);
+        f.evaluate("clear buf here @ - allot").unwrap(); // give the space back
+        assert_eq!(f.evaluate("-100 allot"), Err(ForthError::Exception(-8)));
+        assert_eq!(f.evaluate(": fill-up begin 0 , again ; fill-up"), Err(ForthError::Exception(-8)));
+        assert_eq!(f.evaluate("buf 9999999 erase"), Err(ForthError::Exception(-9)));
+        // string addresses are plain offsets, so the first S" on a line is at TMP, and data space lies above them
+        f.evaluate("clear s\" abc\" 264 c@").unwrap();
+        assert_eq!(f.stack(), vec![264, 3, 3]);
+        assert!(f.find_word("buf").unwrap() >= f.string_size);
+    }
+
+    #[test]
+    fn heap_words() {
+        let mut f = forth();
+        f.evaluate("variable a variable b  10 allocate throw a !  20 allocate throw b !  7 a @ 9 + !").unwrap();
+        f.evaluate("a @ 9 + @  b @ a @ -  b @ 19 + @").unwrap();
+        assert_eq!(f.stack(), vec![7, 10, 0]); // blocks are next to each other, and cleared
+        // once b is freed, a grows in place, keeping its contents
+        f.evaluate("clear b @ free  a @ 25 resize  a @ 9 + @  a @ 24 + @").unwrap();
+        assert_eq!(f.stack(), vec![0, f.alloc_start as i64, 0, 7, 0]);
+        // a block that can't grow in place is moved
+        f.evaluate("clear 5 allocate throw b !  a @ 30 resize throw  dup a @ <>  swap dup a ! 9 + @").unwrap();
+        assert_eq!(f.stack(), vec![-1, 7]);
+        // failures are reported by the ior, and leave the block alone
+        f.evaluate("clear 100000 allocate  a @ 100000 resize nip  a @ free  a @ free  b @ 1+ free").unwrap();
+        assert_eq!(f.stack(), vec![0, -59, -61, 0, -60, -60]);
+        assert_eq!(f.heap.stats().blocks, 1);
+        assert_eq!(f.evaluate("-60 throw"), Err(ForthError::Exception(-60)));
+    }
+
+    #[test]
+    fn string_space() {
+        let mut f = forth();
+        // transient strings don't use string space, and each lasts beyond the next
+        f.evaluate("s-unused  s\" abc\" drop  s\" de\" drop  swap c@ swap c@  s-unused").unwrap();
+        let start = f.stack()[0];
+        assert_eq!(f.stack(), vec![start, 3, 2, start]);
+        // forget gives back the name and the literals of the words it removes
+        f.evaluate("clear : greet .\" hello\" ; s-unused forget greet s-unused").unwrap();
+        assert_eq!(f.stack(), vec![start - 12, start]);
+        // running out is an error, not a panic
+        f.evaluate("clear : fill-strings ( s -- ) begin dup s-create drop again ;").unwrap();
+        assert_eq!(f.evaluate("s\" some text\" drop fill-strings"), Err(ForthError::Exception(-256)));
+        f.evaluate("forget fill-strings s-unused").unwrap();
+        assert_eq!(f.stack(), vec![start]);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let mut f = forth();
+        assert_eq!(f.evaluate(": fill 1 recurse ; fill"), Err(ForthError::Exception(-3)));
+        assert_eq!(f.evaluate(": rfill begin 1 >r again ; rfill"), Err(ForthError::Exception(-5)));
+        assert_eq!(f.evaluate(": lfill 1 0 do recurse loop ; lfill"), Err(ForthError::Exception(-5)));
+        // the dictionary survives, and the overflows can be caught
+        f.evaluate("' fill catch ' rfill catch").unwrap();
+        assert_eq!(f.stack(), vec![-3, -5]);
+        f.evaluate("clear 2 3 +").unwrap();
+        assert_eq!(f.stack(), vec![5]);
+    }
+}
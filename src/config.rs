@@ -1,6 +1,12 @@
 // system configuration and command line processing
 
-use crate::engine::TF;
+use crate::engine::{
+    DEFAULT_DATA_SIZE, DEFAULT_STRING_SIZE, MAX_DATA_SIZE, MAX_STRING_SIZE, MIN_DATA_SIZE,
+    MIN_STRING_SIZE, TF,
+};
+use crate::api::ForthError;
+use crate::internals::image::{embedded_image, image_sizes};
+use std::fs;
 use crate::messages::DebugLevel;
 
 use ::clap::{arg, Command};
@@ -10,37 +16,55 @@ const WELCOME_MESSAGE: &str = "Welcome to f2.";
 const EXIT_MESSAGE: &str = "Finished";
 const DEFAULT_CORE: [&str; 3] = ["./corelib.fs", "~/.f2/corelib.fs", "src/forth/corelib.fs"];
 
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
-}
-
 pub struct Config {
     debug_level: DebugLevel,
     loaded_file: String,
+    image_file: String,
+    embedded: Option<Vec<u8>>, // the image appended to a turnkey executable
     core_file: String,
     no_core: bool,
+    entry_word: String,        // run this word instead of the interpreter
+    data_size: Option<usize>,   // cells of data space, if given on the command line
+    string_size: Option<usize>, // characters of string space, if given on the command line
+    quiet: bool,               // suppress the welcome and exit messages
     pub run: bool,
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Config {
     pub fn new() -> Config {
         Config {
             debug_level: DebugLevel::Error,
             loaded_file: "".to_owned(),
+            image_file: "".to_owned(),
+            embedded: None,
             core_file: DEFAULT_CORE[0].to_owned(),
             no_core: false,
+            entry_word: "".to_owned(),
+            data_size: None,
+            string_size: None,
+            quiet: false,
             run: true,
         }
     }
 
     /// process_args handles command line argument processing using the clap library
+    ///     A turnkey executable doesn't process arguments: they belong to the program it runs.
     ///
     pub fn process_args(&mut self) -> &Config {
         // process arguments
         // let msg = Msg::new(); // Create a message handler for argument errors
+        if let Some((image, entry)) = std::env::current_exe().ok().and_then(|exe| embedded_image(&exe)) {
+            self.embedded = Some(image);
+            self.entry_word = entry;
+            self.quiet = true;
+            return self;
+        }
 
         let arguments = Command::new("tForth")
             .version(VERSION)
@@ -53,6 +77,21 @@ impl Config {
             )
             .arg(arg!(-l --library <VALUE>).required(false))
             .arg(arg!(-f --file <VALUE>).required(false))
+            .arg(arg!(-i --image <VALUE>).required(false))
+            .arg(arg!(-e --entry <WORD> "Run WORD instead of the interpreter").required(false))
+            .arg(arg!(-q --quiet "Don't print the welcome and exit messages").required(false))
+            .arg(
+                arg!(--"data-size" <CELLS> "Size of the data space, which holds the dictionary and stacks")
+                    .required(false)
+                    .value_parser(clap::value_parser!(u64).range(MIN_DATA_SIZE as u64..=MAX_DATA_SIZE as u64)),
+            )
+            .arg(
+                arg!(--"string-size" <CHARS> "Size of the string space")
+                    .required(false)
+                    .value_parser(
+                        clap::value_parser!(u64).range(MIN_STRING_SIZE as u64..=MAX_STRING_SIZE as u64),
+                    ),
+            )
             .arg(arg!(-n - -nocore).required(false))
             .get_matches();
 
@@ -76,6 +115,29 @@ impl Config {
             self.no_core = *nc;
         }
 
+        let image = arguments.get_one::<String>("image");
+        if let Some(image) = image {
+            self.image_file = image.clone();
+        }
+
+        let entry = arguments.get_one::<String>("entry");
+        if let Some(entry) = entry {
+            self.entry_word = entry.clone();
+        }
+
+        let quiet = arguments.get_one::<bool>("quiet");
+        if let Some(q) = quiet {
+            self.quiet = *q;
+        }
+
+        if let Some(size) = arguments.get_one::<u64>("data-size") {
+            self.data_size = Some(*size as usize);
+        }
+
+        if let Some(size) = arguments.get_one::<u64>("string-size") {
+            self.string_size = Some(*size as usize);
+        }
+
         let file = arguments.get_one::<String>("file");
         if let Some(file) = file {
             self.loaded_file = file.clone();
@@ -89,35 +151,79 @@ impl Config {
     pub fn run_forth(&mut self) {
         // create and run the interpreter
         // return when finished
-        fn load_file(interpreter: &mut TF, file_name: &str) {
-            TF::u_set_string(
-                interpreter,
-                interpreter.data[interpreter.tmp_ptr] as usize,
-                file_name,
-            );
-            push!(interpreter, interpreter.data[interpreter.tmp_ptr]);
-            interpreter.f_include_file();
-        }
-
-        let mut forth = TF::new();
+        let image = if let Some(image) = self.embedded.take() {
+            Some(Ok(image))
+        } else if !self.image_file.is_empty() {
+            Some(fs::read(&self.image_file).map_err(|_| ForthError::FileNotFound(self.image_file.clone())))
+        } else {
+            None
+        };
+        // unless sizes were given on the command line, an image is loaded with the sizes it was saved with
+        let defaults = (DEFAULT_DATA_SIZE, DEFAULT_STRING_SIZE);
+        let (data_size, string_size) = match &image {
+            Some(Ok(bytes)) => image_sizes(bytes).unwrap_or(defaults),
+            _ => defaults,
+        };
+        let data_size = self.data_size.unwrap_or(data_size);
+        let string_size = self.string_size.unwrap_or(string_size);
+        let mut forth = match TF::with_sizes(data_size, string_size) {
+            Ok(forth) => forth,
+            Err(e) => {
+                eprintln!("{e}");
+                return;
+            }
+        };
         forth.cold_start();
-        if !self.no_core {
+        let mut loaded_image = false;
+        if let Some(image) = image {
+            // an image already contains the core library
+            match image.and_then(|bytes| forth.load_image_bytes(&bytes)) {
+                Ok(()) => loaded_image = true,
+                Err(e) => forth.msg.error("run_forth", &e.to_string(), None::<bool>),
+            }
+        }
+        if !self.no_core && !loaded_image {
             for path in DEFAULT_CORE {
-                load_file(&mut forth, &path);
+                // missing library locations are expected, and errors in the library have already been reported
+                forth.include_file(path).ok();
             }
         }
-        if self.loaded_file != "" {
-            load_file(&mut forth, &self.loaded_file);
+        if !self.loaded_file.is_empty() {
+            if let Err(e) = forth.include_file(&self.loaded_file) {
+                forth.msg.error("run_forth", &e.to_string(), None::<bool>);
+            }
         }
 
-        forth.set_abort_flag(false); // abort flag may have been set by load_file, but is no longer needed.
+        if !self.quiet {
+            println!("{WELCOME_MESSAGE} Version {VERSION}");
+        }
 
-        println!("{WELCOME_MESSAGE} Version {VERSION}");
+        if self.entry_word.is_empty() {
+            // Enter the interactive loop to read and process input
+            // call QUERY to start the r2 engine.
+            forth.f_quit();
+            // Exit when query gets a bye or EOF.
+        } else {
+            self.run_entry(&mut forth);
+        }
+        if !self.quiet {
+            println!("{EXIT_MESSAGE}");
+        }
+    }
 
-        // Enter the interactive loop to read and process input
-        // call QUERY to start the r2 engine.
-        forth.f_quit();
-        // Exit when query gets a bye or EOF.
-        println!("{EXIT_MESSAGE}");
+    /// run_entry executes the entry word of a turnkey program, in place of the interpreter
+    ///
+    fn run_entry(&self, forth: &mut TF) {
+        match forth.find_word(&self.entry_word) {
+            Some(cfa) => {
+                if let Err(e) = forth.execute(cfa) {
+                    forth.msg.error(&self.entry_word, &e.to_string(), None::<bool>);
+                }
+            }
+            None => forth
+                .msg
+                .error("run_forth", "Entry word not found", Some(&self.entry_word)),
+        }
+        forth.f_flush();
     }
 }
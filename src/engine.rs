@@ -1,24 +1,43 @@
 //The tForth interpreter struct and implementation
 
+use crate::api::ForthError;
 use crate::internals::builtin::BuiltInFn;
+use crate::internals::compiler::HeaderMark;
+use crate::internals::heap::Heap;
+use crate::internals::index::WordIndex;
+use crate::internals::memory::DataSpace;
 use crate::messages::Msg;
 use crate::files::{FileHandle, FileMode};
+use std::cell::RefCell;
 use std::time::Instant;
 
 // DATA AREA constants
-pub const DATA_SIZE: usize = 10000;
-pub const STRING_SIZE: usize = 5000;
+//     The sizes are chosen at startup. The data area is split in half: the dictionary counts up from the bottom,
+//     and the stack counts down from the middle (alloc_start) towards it. The heap takes the next quarter, from
+//     alloc_start up to ret_end, and the return stack counts down from the top towards it.
+//     It holds >R values and loop frames; the return addresses of nested definitions are kept in a Vec (calls).
+//     Data space is mapped above the largest string space, so an address tells which space it is in.
+pub const DEFAULT_DATA_SIZE: usize = 10000;
+pub const DEFAULT_STRING_SIZE: usize = 5000;
+pub const MIN_DATA_SIZE: usize = 4000; // enough for the core library
+pub const MAX_DATA_SIZE: usize = 1 << 24;
+pub const MIN_STRING_SIZE: usize = 3600; // enough for the core library and the transient strings
+pub const MAX_STRING_SIZE: usize = 1 << 24;
 pub const BUF_SIZE: usize = 132;
-pub const ALLOC_START: usize = DATA_SIZE / 2;
-pub const STACK_START: usize = ALLOC_START - 1; // stack counts up
-pub const RET_START: usize = DATA_SIZE - 1; // return stack counts downwards
-pub const WORD_START: usize = 0; // data area counts up from the bottom (builtins, words, variables etc.)
+pub const STACK_ROOM: usize = 64; // cells the dictionary leaves free for the stack, so the interpreter can recover
+pub const DATA_BASE: usize = MAX_STRING_SIZE; // the address of the first cell of data space
+pub const WORD_START: usize = DATA_BASE + 32; // data area counts up from here (words, variables etc.). Smaller
+                                              // values are never addresses, so compiled code can tell opcodes
+                                              // from the words it calls
 
-// STRING AREA constants
+// STRING AREA constants: fixed buffers at the bottom of string space, whatever its size
 pub const TIB_START: usize = 0; // Text input buffer, used by readers
 pub const PAD_START: usize = TIB_START + BUF_SIZE; // Scratchpad buffer, used by PARSE and friends
 pub const TMP_START: usize = PAD_START + BUF_SIZE; // Temporary buffer, used for string input
-pub const STR_START: usize = TMP_START + BUF_SIZE; // Free space for additional strings
+pub const TRANSIENT_SIZE: usize = 4 * BUF_SIZE; // TMP is the start of a ring of transient strings, made by S"
+pub const HOLD_START: usize = TMP_START + TRANSIENT_SIZE; // Pictured numeric output, built down from HOLD_END
+pub const HOLD_END: usize = HOLD_START + BUF_SIZE;
+pub const STR_START: usize = HOLD_END; // Free space for additional strings
 
 // GENERAL constants
 pub const TRUE: i64 = -1; // forth convention for true and false
@@ -26,27 +45,79 @@ pub const FALSE: i64 = 0;
 pub const ADDRESS_MASK: usize = 0x00FFFFFFFFFFFFFF; // to get rid of flags
 pub const IMMEDIATE_MASK: usize = 0x4000000000000000; // the immediate flag bit
 pub const BUILTIN_MASK: usize = 0x2000000000000000; // the builtin flag bit
+pub const TAIL_CALL_MASK: usize = 0x4000000000000000; // in compiled code, a call that jumps, because nothing follows it
+pub const WORDLIST_MASK: usize = 0x1F00000000000000; // the wordlist a name field belongs to
+pub const WORDLIST_SHIFT: u32 = 56;
+pub const MAX_WORDLISTS: usize = 32; // as many as fit in WORDLIST_MASK
+pub const MAX_ORDER: usize = 8; // the longest search order
+pub const FORTH_WORDLIST: i64 = 0;
+pub const OVERFLOW_WRAPPING: i64 = 0; // values of OVERFLOW-MODE, which decides what + - and * do on overflow
+pub const OVERFLOW_CHECKED: i64 = 1;
+pub const OVERFLOW_SATURATING: i64 = 2;
+pub const MAX_CALL_DEPTH: usize = 1 << 16; // nested definitions, before a return stack overflow
+pub const MAX_FLOAT_DEPTH: usize = 64; // the floating-point stack is kept separately, on the Rust side
 //pub const FILE_MODE_R_W: i64 = -1;
 pub const FILE_MODE_R_O: i64 = 0;
 
-// Indices into builtins to drive execution of each data type
-pub const BUILTIN: i64 = 100000;
-pub const VARIABLE: i64 = 100001;
-pub const CONSTANT: i64 = 100002;
-pub const LITERAL: i64 = 100003;
-pub const STRLIT: i64 = 100004;
-pub const DEFINITION: i64 = 100005;
-pub const BRANCH: i64 = 100006;
-pub const BRANCH0: i64 = 100007;
-pub const ABORT: i64 = 100008;
-pub const EXIT: i64 = 100009;
-pub const BREAK: i64 = 100010;
+// Opcodes: the code field of each kind of word, and the operations compiled into definitions
+//     They are small and dense, so the inner interpreter can dispatch on them with a jump table.
+//     Any other value in a definition is the cfa of a word to call, or a builtin overlaid with BUILTIN_MASK.
+pub const BUILTIN: i64 = 0;
+pub const VARIABLE: i64 = 1;
+pub const CONSTANT: i64 = 2;
+pub const LITERAL: i64 = 3;
+pub const STRLIT: i64 = 4;
+pub const DEFINITION: i64 = 5;
+pub const BRANCH: i64 = 6;
+pub const BRANCH0: i64 = 7;
+pub const ABORT: i64 = 8;
+pub const EXIT: i64 = 9;
+pub const BREAK: i64 = 10;
+pub const DO: i64 = 11;      // counted loops keep [index][limit] on the return stack, index on top
+pub const QDO: i64 = 12;     // followed by an offset to the end of the loop
+pub const LOOP: i64 = 13;    // followed by an offset back to the start of the loop
+pub const PLUS_LOOP: i64 = 14;
+pub const LEAVE: i64 = 15;   // followed by an offset to the end of the loop
+pub const DOES: i64 = 16;    // made by CREATE: followed by the address of the DOES> code, or 0, then the data
+pub const FLITERAL: i64 = 17; // followed by the bits of an f64
+// Superinstructions, made by the optimizer when a definition is finished, each doing the work of two words
+pub const LIT_PLUS: i64 = 18;    // LITERAL n + : followed by n
+pub const LIT_MINUS: i64 = 19;   // LITERAL n - : followed by n
+pub const DUP_BRANCH0: i64 = 20; // dup BRANCH0 : followed by the offset, and branches without consuming the flag
+pub const OVER_OVER: i64 = 21;   // over over
+pub const R_FROM_DROP: i64 = 22; // r> drop
+
+// Exception codes for THROW and CATCH, using the standard Forth values
+pub const ERR_ABORT: i64 = -1;
+pub const ERR_ABORT_Q: i64 = -2;
+pub const ERR_STACK_OVERFLOW: i64 = -3;
+pub const ERR_STACK_UNDERFLOW: i64 = -4;
+pub const ERR_RETURN_OVERFLOW: i64 = -5;
+pub const ERR_RETURN_UNDERFLOW: i64 = -6;
+pub const ERR_DICTIONARY_OVERFLOW: i64 = -8;
+pub const ERR_INVALID_ADDRESS: i64 = -9;
+pub const ERR_DIVIDE_BY_ZERO: i64 = -10;
+pub const ERR_OUT_OF_RANGE: i64 = -11;
+pub const ERR_UNDEFINED_WORD: i64 = -13;
+pub const ERR_HOLD_OVERFLOW: i64 = -17;
+pub const ERR_UNSUPPORTED: i64 = -21;
+pub const ERR_INVALID_ARGUMENT: i64 = -24;
+pub const ERR_FILE_IO: i64 = -37;
+pub const ERR_ALLOCATE: i64 = -59;
+pub const ERR_FREE: i64 = -60;
+pub const ERR_RESIZE: i64 = -61;
+pub const ERR_FLOAT_OVERFLOW: i64 = -44;
+pub const ERR_FLOAT_UNDERFLOW: i64 = -45;
+pub const ERR_ORDER_OVERFLOW: i64 = -49;
+pub const ERR_ORDER_UNDERFLOW: i64 = -50;
+pub const ERR_STRING_SPACE: i64 = -256; // the first code the standard leaves to the system
 
 /// The primary data structure for the Forth engine
 ///
 ///     Forth's main data structure is a fixed array of integers (overloaded with characters and unsigned values).
 ///     This holds all the program data - words, variables, constants, stack etc. used by Forth
-///     Strings are kept in a separate array, which is simpler than packing ASCII characters into 64 bit words
+///     Strings are kept in a separate array, which is simpler than packing ASCII characters into 64 bit words.
+///     It shares one address space with the data array, which is mapped above it, so c@ and c! work on either.
 ///     The Rust side of the engine keeps track of some variables with names following a *_ptr pattern.
 ///     This allows these values to be easily used by both Rust and Forth.
 ///     A small reader module manages input from files and stdin. Unfortunatly there is no easy way to provide
@@ -56,13 +127,22 @@ pub const BREAK: i64 = 100010;
 ///
 //#[derive(Debug)]
 pub struct TF {
-    pub data: [i64; DATA_SIZE],
-    pub strings: [char; STRING_SIZE], // storage for strings
+    pub data: DataSpace,              // storage for cells, addressed from DATA_BASE
+    pub strings: Vec<char>,           // storage for strings, addressed from 0
+    pub data_size: usize,             // number of cells in data
+    pub string_size: usize,           // number of characters in strings
+    pub alloc_start: usize,           // the middle of the data area: dictionary below, heap above
+    pub ret_end: usize,               // the top of the heap: the return stack is full when return_ptr gets here
+    pub stack_start: usize,           // the stack is empty when stack_ptr is here, and counts down
+    pub ret_start: usize,             // the return stack is empty when return_ptr is here, and counts down
     pub builtins: Vec<BuiltInFn>,     // the dictionary of builtins
     //pub return_stack: Vec<i64>,     // for do loops etc.
     pub here_ptr: usize,              // first free cell at top of dictionary
     pub stack_ptr: usize,             // top of the linear space stack
     pub return_ptr: usize,            // top of the return stack
+    pub calls: Vec<usize>,            // return addresses of the definitions being run, kept apart from the return stack
+    pub heap: Heap,                   // the blocks given out by ALLOCATE
+    pub floats: Vec<f64>,             // the floating-point stack, top last
     pub context_ptr: usize,           // nfa of most recent word
     pub eval_ptr: usize,              // used to turn compile mode on and off
     pub base_ptr: usize,              // for numeric I/O
@@ -70,10 +150,10 @@ pub struct TF {
     pub tmp_ptr: usize,               // temporary string buffer
     pub string_ptr: usize,            // points to the beginning of free string space
     pub last_ptr: usize,              // points to name of top word
-    pub hld_ptr: usize,               // for numeric string work
+    pub hld_ptr: usize,               // the start of the pictured numeric output string, in string space
     pub state_ptr: usize,             // true if compiling a word
     pub pc_ptr: usize,                // program counter
-    pub abort_ptr: usize,             // true if abort has been called
+    pub abort_ptr: usize,             // non-zero (the exception code) if abort or throw has been called
     pub tib_ptr: usize,               // TIB
     pub tib_size_ptr: usize,
     pub tib_in_ptr: usize,
@@ -83,45 +163,99 @@ pub struct TF {
     pub files: Vec<FileHandle>,       // keeps track of open files
     pub show_stack: bool,             // show the stack at the completion of a line of interaction
     pub stepper_ptr: usize,
+    pub current_ptr: usize,           // the wordlist new definitions are added to
+    pub order_ptr: usize,             // the length of the search order, followed by the wordlists, first searched first
+    pub wordlists_ptr: usize,         // the number of wordlists created so far
+    pub overflow_ptr: usize,          // what integer arithmetic does on overflow: wrap, throw or saturate
     pub timer: Instant,               // for timing things
+    pub catch_depth: usize,           // number of active CATCH frames
+    pub current_builtin: usize,       // index of the builtin being run, so a stack overflow can name it
+    pub open_word: usize,             // nfa of a word made by CREATE whose back pointer isn't written yet, or 0
+    pub header_mark: Option<HeaderMark>, // the dictionary before the definition being compiled, to undo it on an error
+    pub index: RefCell<WordIndex>,    // hashed lookup of the words in the dictionary
 }
 
 
+impl Default for TF {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TF {
     // ForthInterpreter struct implementations
     pub fn new() -> TF {
+        TF::with_sizes(DEFAULT_DATA_SIZE, DEFAULT_STRING_SIZE)
+            .expect("default memory sizes are valid")
+    }
+
+    /// with_sizes creates an interpreter with data_size cells of data space and string_size characters of string space
+    ///     The stacks and the dictionary share data space, so the stack regions grow with it.
+    ///
+    pub fn with_sizes(data_size: usize, string_size: usize) -> Result<TF, ForthError> {
+        if !(MIN_DATA_SIZE..=MAX_DATA_SIZE).contains(&data_size) {
+            return Err(ForthError::InvalidSize(format!(
+                "data size {data_size} is not between {MIN_DATA_SIZE} and {MAX_DATA_SIZE} cells"
+            )));
+        }
+        if !(MIN_STRING_SIZE..=MAX_STRING_SIZE).contains(&string_size) {
+            return Err(ForthError::InvalidSize(format!(
+                "string size {string_size} is not between {MIN_STRING_SIZE} and {MAX_STRING_SIZE} characters"
+            )));
+        }
+        let alloc_start = DATA_BASE + data_size / 2;
+        let ret_end = alloc_start + data_size / 4;
         let mut interpreter = TF {
-            data: [0; DATA_SIZE],
-            strings: [' '; STRING_SIZE],
+            data: DataSpace::new(data_size),
+            strings: vec![' '; string_size],
+            data_size,
+            string_size,
+            alloc_start,
+            ret_end,
+            stack_start: alloc_start - 1,
+            ret_start: DATA_BASE + data_size - 1,
             builtins: Vec::new(),
             here_ptr: WORD_START,
-            stack_ptr: STACK_START,
-            return_ptr: RET_START,
-            string_ptr: 0,
-            context_ptr: 0,
-            eval_ptr: 0,
-            base_ptr: 0,
-            pad_ptr: 0,
-            tmp_ptr: 0,
-            last_ptr: 0,
-            hld_ptr: 0,
-            state_ptr: 0,
-            pc_ptr: 0,
-            abort_ptr: 0,
-            tib_ptr: 0,
-            tib_size_ptr: 0,
-            tib_in_ptr: 0,
+            stack_ptr: alloc_start - 1,
+            return_ptr: DATA_BASE + data_size - 1,
+            calls: Vec::new(),
+            heap: Heap::new(alloc_start, ret_end),
+            floats: Vec::with_capacity(MAX_FLOAT_DEPTH),
+            // the variable pointers read the first data cell until cold start sets them
+            string_ptr: DATA_BASE,
+            context_ptr: DATA_BASE,
+            eval_ptr: DATA_BASE,
+            base_ptr: DATA_BASE,
+            pad_ptr: DATA_BASE,
+            tmp_ptr: DATA_BASE,
+            last_ptr: DATA_BASE,
+            hld_ptr: DATA_BASE,
+            state_ptr: DATA_BASE,
+            pc_ptr: DATA_BASE,
+            abort_ptr: DATA_BASE,
+            tib_ptr: DATA_BASE,
+            tib_size_ptr: DATA_BASE,
+            tib_in_ptr: DATA_BASE,
             exit_flag: false,
             msg: Msg::new(),
             reader: Vec::new(),
             files: Vec::new(),
             show_stack: true,
-            stepper_ptr: 0,
+            stepper_ptr: DATA_BASE,
+            current_ptr: DATA_BASE,
+            order_ptr: DATA_BASE,
+            wordlists_ptr: DATA_BASE,
+            overflow_ptr: DATA_BASE,
             timer: Instant::now(),
+            catch_depth: 0,
+            current_builtin: usize::MAX,
+            open_word: 0,
+            header_mark: None,
+            index: RefCell::new(WordIndex::default()),
         };
         let fh = FileHandle::new(None, Msg::new(), FileMode::RO).expect("Can't access stdout");
         interpreter.reader.push(fh); // Indicates stdin is active
-        interpreter
+        Ok(interpreter)
     }
 
     /// cold_start is where the interpreter begins, installing some variables and the builtin functions.
@@ -148,11 +282,7 @@ impl TF {
     ///     In this version, the STATE variable is used directly.
     ///
     pub fn get_compile_mode(&mut self) -> bool {
-        if self.get_var(self.state_ptr) == FALSE {
-            false
-        } else {
-            true
-        }
+        self.get_var(self.state_ptr) != FALSE
     }
 
     /// set_compile_mode turns on compilation mode
@@ -162,6 +292,7 @@ impl TF {
     }
 
     /// set_abort_flag allows the abort condition to be made globally visible
+    ///     ABORT? holds the code of the pending exception; setting the flag raises ABORT (-1).
     ///
     pub fn set_abort_flag(&mut self, v: bool) {
         self.set_var(self.abort_ptr, if v { -1 } else { 0 });
@@ -171,11 +302,7 @@ impl TF {
     ///
     pub fn get_abort_flag(&mut self) -> bool {
         let val = self.get_var(self.abort_ptr);
-        if val == FALSE {
-            false
-        } else {
-            true
-        }
+        val != FALSE
     }
 
     /// should_exit determines whether or not the user has executed BYE
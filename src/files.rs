@@ -71,7 +71,7 @@ impl FileHandle {
                             "File not able to be opened",
                             Some(file_path),
                         );
-                        return None;
+                        None
                     }
                 }
             }
@@ -0,0 +1,38 @@
+( Benchmarks for the inner interpreter )
+( include src/forth/benchmarks.fs, then run benchmarks to time each one in milliseconds. )
+( Build with cargo build --release to get meaningful times. )
+
+: bench-fib ( -- )  25 fib drop ;                 \ about 250,000 calls: measures calling and returning
+
+1000 constant sieve-size
+: cells-of ( n -- ) 0 do 0 , loop ;
+create sieve-flags sieve-size cells-of
+
+: sieve ( -- n )                                   \ counts the primes below 2 * sieve-size + 3
+                    sieve-size 0 do 1 sieve-flags i + ! loop
+                    0 sieve-size 0 do
+                        sieve-flags i + @ if
+                            i 2* 3 + dup i +           \ the prime, and the index of its first odd multiple
+                            begin dup sieve-size < while
+                                0 over sieve-flags + ! over +
+                            repeat
+                            2drop 1+
+                        then
+                    loop ;
+: bench-sieve ( -- ) 200 0 do sieve drop loop ;    \ measures loops, branches and memory access
+
+s" The quick brown fox jumps over the lazy dog, then the lazy dog sleeps in the evening sun." drop s-create
+constant bench-text
+variable target
+: count-char ( s c -- n )                          \ counts the occurrences of c in the counted string s
+                    target ! 0 swap dup c@ 1+ 1 ?do
+                        dup i + c@ target @ = if swap 1+ swap then
+                    loop drop ;
+: bench-strings ( -- )                             \ measures character access and string copying
+                    5000 0 do bench-text dup c@ tmp @ s-move drop tmp @ 'e' count-char drop loop ;
+
+: time-it ( xt -- )  now execute millis 6 .r ."  ms" cr ;
+: benchmarks ( -- )
+                    ." fib     " ['] bench-fib time-it
+                    ." sieve   " ['] bench-sieve time-it
+                    ." strings " ['] bench-strings time-it ;
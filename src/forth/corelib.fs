@@ -1,7 +1,6 @@
 : parse pad @ swap parse-to ;                       
 : \ 1 parse drop drop ; immediate                  
 : ( 41 parse drop drop ; immediate                  \ Implements in-line comments
-: [char] BL parse drop drop pad @ 1 + c@ ;          \ Place the first char of the next token on the stack
 
 1 dbg \ set debuglevel to warnings and errors
 
@@ -14,26 +13,34 @@
         last @ context !              \ update CONTEXT
         ;
 
-: constant ( n -- ) create 100002 , ,      \ v constant <name> creates a constant with value v
+: constant ( n -- ) (create) 2 , ,      \ v constant <name> creates a constant with value v
     (close) ;  
 
 0 constant FALSE
 -1 constant TRUE
 
 \ Constants referring to inner interpreter opcodes, which are typically compiled into definitions
-100000 constant BUILTIN
-100001 constant VARIABLE
-100002 constant CONSTANT
-100003 constant LITERAL
-100004 constant STRLIT
-100005 constant DEFINITION
-100006 constant BRANCH
-100007 constant BRANCH0
-100008 constant ABORT
-100009 constant EXIT
-100010 constant BREAK
+0 constant BUILTIN
+1 constant VARIABLE
+2 constant CONSTANT
+3 constant LITERAL
+4 constant STRLIT
+5 constant DEFINITION
+6 constant BRANCH
+7 constant BRANCH0
+8 constant ABORT
+9 constant EXIT
+10 constant BREAK
+11 constant DO
+12 constant ?DO
+13 constant LOOP
+14 constant +LOOP
+15 constant LEAVE
+16 constant DOES
+17 constant FLITERAL
 
 72057594037927935 constant ADDRESS_MASK                      \ wipes any flags
+2233785415175766016 constant WORDLIST_MASK                    \ the wordlist a name field belongs to
 
 \ ASCII symbols that are useful for text processing
 10 constant '\n'
@@ -49,14 +56,20 @@
 91 constant '['
 93 constant ']'
 
+: [char] BL parse drop drop pad @ 1 + c@ ;          \ Place the first char of the next token on the stack
+
 \ For file I/O
 -1 constant R/W
  0 constant R/O
  1 constant W/O
 
- : variable ( -- ) create VARIABLE , 0 ,    \ variable <name> creates a variable, initialized to zero
+ : variable ( -- ) (create) VARIABLE , 0 ,    \ variable <name> creates a variable, initialized to zero
     (close) ;  
 
+0 constant WRAPPING                                          \ values for overflow-mode
+1 constant CHECKED
+2 constant SATURATING
+
 : decimal 10 base ! ;
 : hex 16 base ! ;
 
@@ -64,7 +77,7 @@
 
 : text              BL parse ;                                \ Parser shortcut for space-delimited tokens
 : s-parse           tmp @ swap parse-to ;                     \ Same as text, but loads to tmp instead of pad
-: s" ( -- s u ")    tmp @ '"' parse-to ;                      \ Places a double-quoted string in tmp
+: s" ( -- s u ")    tmp @ '"' parse-to s-transient ;          \ Places a double-quoted string in a transient buffer
 
 ( File reader functions )
 : included          tmp @ include-file ; \ include-file uses a string pointer on the stack to load a file
@@ -83,12 +96,16 @@
 : else              BRANCH , here @ 0 , swap dup here @ swap - swap ! ; immediate
 : then dup here @ swap - swap ! ; immediate
 
-: ' (')             dup @ dup DEFINITION = if drop else nip then ; \ searches for a (postfix) word and returns its cfa or FALSE
+: ' (')             dup @ dup DEFINITION = over DOES = or if drop else nip then ; \ searches for a (postfix) word and returns its cfa or FALSE
 
 : [']               LITERAL , ' , ; immediate                        \ compiles a word's cfa into a definition as a literal
+: does>            LITERAL , here @ 3 + ,                   \ the DOES> code starts after (does>) and BREAK
+                    ['] (does>) , BREAK , ; immediate          \ gives the word made by CREATE the code that follows
+: >body ( xt -- a ) 2 + ;                                   \ the data field of a word made by CREATE
 : cfa>nfa           1 - ;                                        \ converts an cfa to an nfa
 : nfa>cfa           1 + ;                                        \ converts an nfa to a cfa
 : bp>nfa            1 + ;                                         \ from preceding back pointer to nfa
+: nfa>wid           @ WORDLIST_MASK and 72057594037927936 / ;    \ the wordlist a word belongs to
 : bp>cfa            2 + ; 
 
 : 1- ( n -- n-1 )   1 - ;
@@ -99,7 +116,7 @@
 : 2dup ( a b -- a b a b ) over over ;
 : 2drop ( a b -- )  drop drop ;
 : ?dup              dup 0= if else dup then ;
-: rdrop ( -- )      r> drop ;                           \ Pop a return address off the stack
+: rdrop ( -- )      r> drop ;                           \ Drop the top of the return stack
 : exit ( -- )       BREAK , ; immediate                 \ Pop out of the current definition and reset the Program Counter
 : >                 swap < ;
 : <> ( n -- n )     = 0= ;
@@ -109,13 +126,23 @@
 : max ( m n -- m | n ) 2dup > if drop else nip then ;
 : abs ( n -- n | -n ) dup 0 < if -1 * then ;
 
-: for               here @ ['] >r , ; immediate
+\ Data space is addressed in cells, and a character takes a whole cell, so every address is aligned
+: cells ( n -- n )   ;                                  \ the size of n cells in address units
+: cell+ ( a -- a )   1+ ;
+: chars ( n -- n )   ;                                  \ the size of n characters in address units
+: char+ ( a -- a )   1+ ;
+: aligned ( a -- a ) ;
+: align ( -- )       ;
+: c, ( c -- )        255 and , ;                        \ compile a character into the next cell
+
+: for               LITERAL , 0 , ['] >r ,             \ a dummy limit makes the frame two cells, like do
+                    here @ ['] >r , ; immediate
 : next              ['] r> , 
                     LITERAL , 1 , 
                     ['] - , ['] dup , 
                     ['] 0= , BRANCH0 , 
                     here @ - , 
-                    ['] drop , ; immediate
+                    ['] drop , ['] r> , ['] drop , ; immediate
 
 : begin             here @ ; immediate
 : until             BRANCH0 , here @ - , ; immediate
@@ -123,6 +150,20 @@
 : while             BRANCH0 , here @ 0 , ;  immediate
 : repeat            BRANCH , swap here @ - , dup here @ swap - swap ! ; immediate
 
+\ Counted loops keep [index][limit] on the return stack. LEAVE and ?DO branch to the end of the loop,
+\ which isn't known until LOOP is compiled, so their offset cells are chained through (leaves) until then.
+variable (leaves)                                       \ offset cells waiting for the end of the innermost loop
+: (leave,) ( -- )   here @ (leaves) @ , (leaves) ! ;    \ compile a link into the chain
+: (resolve) ( -- )  (leaves) @                          \ point each offset in the chain at here
+                    begin ?dup while 
+                        dup @ swap here @ over - swap ! 
+                    repeat ;
+: do                DO , (leaves) @ 0 (leaves) ! here @ ; immediate
+: ?do               ?DO , (leaves) @ 0 (leaves) ! (leave,) here @ ; immediate
+: leave             LEAVE , (leave,) ; immediate
+: loop              LOOP , here @ - , (resolve) (leaves) ! ; immediate
+: +loop             +LOOP , here @ - , (resolve) (leaves) ! ; immediate
+
 : case              0 ; immediate
 : of                ['] over ,
                     ['] = ,
@@ -180,38 +221,16 @@
 
 \ mumeric functions
 
-: /mod              2dup mod rot rot / ;
-
-: .d ( n -- )       dup 10 < 
-                    if '0' else 10 - 'A' then
-                    + emit ;    \ print a single digit
-
-: .- ( -- )         '-' emit ;       \ print a minus sign
-
-: u.    ( u -- )    base @ /mod
-                    ?dup if recurse then .d ;
+: dabs ( d -- ud )  dup 0< if dnegate then ;
 
-: uwidth ( u -- n ) \ returns the number of digits in an unsigned number
-                    base @ / 
-                    ?dup if recurse 1+ else  1 then ;
-
-: u.r ( u width -- )
-                    swap dup uwidth rot swap -
-                    spaces u. ;
-
-: .r ( n width -- )
-                    swap dup 0< 
-                    if 
-                        negate 1 swap rot 1-
-                    else
-                        0 swap rot
-                    then
-                    swap dup uwidth rot swap -
-                    spaces swap
-                    if '-' emit then
-                    u. ;
-
-: . 0 .r space ;
+: d.r ( d width -- ) >r swap over dabs                     \ keep the high cell for its sign
+                    <# #s rot sign #>
+                    r> over - spaces tell ;
+: d. ( d -- )       0 d.r space ;
+: .r ( n width -- ) >r s>d r> d.r ;
+: . ( n -- )        s>d d. ;
+: u.r ( u width -- ) >r 0 <# #s #> r> over - spaces tell ;
+: u. ( u -- )       0 u.r space ;
 
 : +! ( n addr -- )  dup @ rot + swap ! ;
 : ?  ( addr -- )    @ . ;
@@ -225,11 +244,13 @@ variable word-counter
                     word-counter @ 8 mod
                     if space else cr then @ ;   
 
-: words ( -- )
+: words ( -- )                                 \ lists the words in the first wordlist of the search order
                     0 word-counter !
-                    here @ 1- @                                 \ Get the starting point: the top back pointer
+                    last @ 1-                                   \ Get the starting point: the link before the last word
                     begin                                       \ loops through the words in the dictionary
-                        .word dup not                           \ print a word and test the next pointer
+                        dup bp>nfa nfa>wid #order 1+ @ = 
+                        if .word else @ then                    \ print a word if it's in the wordlist, and get the next pointer
+                        dup not
                     until 
                         drop ;   
 
@@ -248,27 +269,38 @@ variable word-counter
 : debug             show-stack step-on ;
 
 : system" ( <command> ) tmp @ '"' parse-to drop (system) ;
+: save-image ( <file> ) tmp @ 32 parse-to drop (save-image) ;  \ save the dictionary, to be loaded with --image
+: turnkey ( <file> <word> ) tmp @ 32 parse-to drop pad @ 32 parse-to drop (turnkey) ; \ save an executable that runs <word>
 : sec ( n -- )      1000 * ms ;  \ sleep for n seconds
 
-: abort" STRLIT , s" drop s-create , ['] type , ['] abort , ; immediate \ abort with a message. Use inside another word.
+: abort" STRLIT , s" drop s-create , ['] (abort") , ; immediate \ abort with a message. Use inside another word.
 
 : forget-last ( -- )                            \ delete the most recent definition
-                    here @ 1- @ dup 1+ here !                   \ resets HERE to the previous back pointer
-                    @ 1+ dup context ! last !                   \ resets CONTEXT and LAST
+                    last @ dup here !                           \ resets HERE to the name field of the last word
+                    dup @ ADDRESS_MASK and s-here !             \ and S-HERE to its name, reclaiming its strings
+                    1- @ 1+ dup context ! last !                \ resets CONTEXT and LAST
                     ;
 
 : forget ( <name> )                             \ delete <name> and any words since
                     trace-off step-off          \ we're messing with the dictionary, so we don't want to run FIND
                     (') dup  
                     if 
-                        1- dup dup here ! @ s-here !            \ move to nfa and set HERE and S-HERE
+                        1- dup dup here ! @ ADDRESS_MASK and s-here !   \ move to nfa and set HERE and S-HERE
                         1- @ 1+ dup context ! last !            \ go back a link and set CONTEXT and LAST
                     else
-                        drop ;
+                        drop
+                    then ;
+
+\ Vocabularies name wordlists. Executing one replaces the first wordlist in the search order,
+\ so "also <vocabulary>" adds it to the search order and "<vocabulary> definitions" compiles into it.
+: (vocabulary) ( wid -- )                       \ replace the first wordlist in the search order with wid
+                    >r get-order ?dup if nip else 1 then r> swap set-order ;
+: vocabulary ( <name> ) wordlist create , does> @ (vocabulary) ;
+: forth ( -- )      forth-wordlist (vocabulary) ;
 
 \ : ?stack depth 0= if abort" Stack underflow" then ;
 
-: kkey ( -- c )     >in @ c@ 1 >in +! ;                         \ Get the next character from the TIB
+: kkey ( -- c )     'tib @ >in @ + c@ 1 >in +! ;               \ Get the next character from the TIB
 : ?key ( -- c T | F )                                           \ If there's a character in TIB, push it and TRUE
                     #tib @ >in @ < if FALSE else key TRUE then ; \ otherwise push FALSE
 : strlen ( s -- n ) c@ ;                                        \ return the count byte from the string
@@ -34,6 +34,13 @@ variable test-num 0 test-num !
             next i . 
         i 
     next ;
+: do-sum ( limit start -- n ) 0 rot rot do i + loop ;
+: ?do-sum ( limit start -- n ) 0 rot rot ?do i + loop ;
+variable step
+: step-sum ( limit start step -- n ) step ! 0 rot rot do i + step @ +loop ;
+: leave-test ( -- n ) 100 0 do i 5 = if i leave then loop ;
+: unloop-test ( -- n ) 10 0 do i 3 = if i unloop exit then loop 0 ;
+: ijk-test ( -- n ) 2 0 do 2 0 do 2 0 do k 100 * j 10 * + i + loop loop loop + + + + + + + ;
 
 
 ."         Clear has to be the first test" cr
@@ -61,16 +68,38 @@ variable test-num 0 test-num !
 7 21 7 loop-test + + + + +  test-dual
 4 2 4 nested-loop-test - - test-dual
 3 3 3 loop-test + test-dual
+45 10 0 do-sum test-single
+0 5 5 ?do-sum test-single
+20 10 0 2 step-sum test-single
+30 0 10 -2 step-sum test-single
+5 leave-test test-single
+3 unloop-test test-single
+444 ijk-test test-single
 
 ."         Arithmetic" cr
 5 1 4 + test-single
 -10 5 15 - test-single
 -20 2 -10 * test-single
 4 12 3 / test-single
+-9223372036854775808 9223372036854775807 1 + test-single
+9223372036854775807 SATURATING overflow-mode ! 9223372036854775807 1 + WRAPPING overflow-mode ! test-single
+-3 -7 2 / test-single
+-1 -7 2 mod test-single
+-1 -3 -7 2 /mod test-dual
+1 -4 -7 s>d 2 fm/mod test-dual
 
 ."         Logic" cr
 -1 TRUE test-single
 0 FALSE test-single
+6 5 3 xor test-single
+-1 0 invert test-single
+12 12 15 and test-single
+-9223372036854775808 1 63 lshift test-single
+15 -1 60 rshift test-single
+0 -1 64 rshift test-single
+-4 -16 2 arshift test-single
+-2 -3 2/ test-single
+6 3 2* test-single
 
 ."         Comparisons" cr
 FALSE 1 3 > test-single
@@ -84,6 +113,13 @@ TRUE 0 0= test-single
 FALSE 0 0<> test-single
 TRUE 5 0<> test-single
 TRUE -22 0< test-single
+TRUE 1 -1 u< test-single
+FALSE 1 -1 u> test-single
+5 -1 5 umin test-single
+-1 -1 5 umax test-single
+TRUE 5 1 10 within test-single
+FALSE 10 1 10 within test-single
+TRUE -1 -5 5 within test-single
 FALSE 0 0< test-single
 FALSE 55 0< test-single
 
@@ -116,12 +152,109 @@ FALSE 55 0< test-single
 ."        Constants" cr
 12 12 constant months months test-single \ a constant with the value 12
 
+."        Defining words" cr
+: array ( n -- ) create 0 do 0 , loop does> swap + ;
+: enum ( n -- n+1 ) create dup , 1+ does> @ ;
+7 3 array arr 7 1 arr ! 1 arr @ test-single
+2 0 enum red enum green enum blue drop blue test-single
+30 create table 10 , 20 , 30 , table 2 + @ test-single
+20 : middle table 1 + @ ; middle test-single
+1 ' green >body @ test-single
+
+."        Wordlists" cr
+vocabulary test-voc
+also test-voc definitions
+: square ( n -- n ) dup * ;
+: dup ( n -- n n ) 0 ;                  \ hides the forth dup while test-voc is searched first
+forth-wordlist set-current
+9 3 square test-single
+0 5 dup nip test-single
+previous
+5 5 dup drop test-single
+1 get-order nip test-single             \ test-voc was removed; forth is left
+only
+
+."        Number input" cr
+255 hex FF decimal test-single
+255 hex ff decimal test-single
+16 $10 test-single
+-31 $-1F test-single
+5 %101 test-single
+10 hex #10 decimal test-single
+97 'a' test-single
+-1 $FFFFFFFFFFFFFFFF test-single
+: prefixes $FF %11 #-9 ;
+255 3 prefixes drop test-dual
+
+."        Double numbers" cr
+123 0 123. test-dual
+-5 -1 -5. test-dual
+-2 0 9223372036854775807 2 m* test-dual
+1 -4 -7 s>d 2 fm/mod test-dual
+-1 -3 -7 s>d 2 sm/rem test-dual
+0 -1 -1 -1 um* -1 um/mod test-dual
+1000000000000000000 1000000000000 1000000000000 1000000 */ test-single
+1 12 17 5 7 */mod test-dual
+3 0 1. 2. d+ test-dual
+-1 -1 1. 2. d- test-dual
+-1 1. 2. d< test-single
+0 -1. -2. d< test-single
+: d-lit 9223372036854775808. ;
+-9223372036854775808 0 d-lit test-dual
+-9223372036854775808 -1 d-lit dnegate test-dual
+
+."        Pictured output" cr
+3 255 0 <# #s #> nip test-single
+1 0 0 <# #s #> nip test-single
+20 -1 0 <# #s #> nip test-single
+64 -1 2 base ! 0 <# #s #> decimal nip test-single
+'F' 255 hex 0 <# #s #> decimal drop c@ test-single
+'-' -5 s>d swap over dabs <# #s rot sign #> drop c@ test-single
+: hex4 ( u -- s u ) base @ >r hex 0 <# # # # # #> r> base ! ;
+4 '0' 255 hex4 swap c@ test-dual
+'%' 42 0 <# '%' hold #s #> + 1- c@ test-single
+
+."        Memory" cr
+create mem-buf 8 cells allot
+'z' mem-buf 4 'z' fill mem-buf 3 + c@ test-single
+0 mem-buf 4 erase mem-buf 3 cells + @ test-single
+'e' s" hello" drop 1+ mem-buf 5 move mem-buf cell+ c@ test-single
+2 here @ 1 c, 2 c, here @ swap - test-single
+0 5 allocate drop free test-single
+-60 5 allocate drop dup free drop free test-single
+3 s" abc" drop s" de" 2drop c@ test-single                \ a transient string outlasts the next one
+
+."        Floats" cr
+: f-test ( F: r1 r2 -- ) ( -- n ) f* 10e0 f* f>s ;
+75 1.5e0 5e f-test test-single
+-25 -25 s>f f>s test-single
+-2 -2.5e0 f>s test-single
+3 9e fsqrt f>s test-single
+-1 1e 2e f< test-single
+0 2e 1e f< test-single
+-1 1e 1e f- f0= test-single
+1 0e fexp fln fcos f>s test-single
+0 0e fsin f>s test-single
+2 1e fdup f+ f>s test-single
+1 1e 2e fswap f- f>s test-single
+4 4e 3e fdrop f>s test-single
+variable fv 6.5e fv f!
+6 fv f@ f>s test-single
+
 ."        Engine" cr
 264 s" does-not-exist" drop ?unique test-single
 264 s" *" drop ?unique test-single
 264 s" min" drop ?unique test-single
 : exit-test 22 33 exit 44 ;
 22 33 exit-test test-dual
+: opt-test ( n -- n ) dup if 10 + else 1 - then ;     \ fused by the optimizer
+15 5 opt-test test-single
+-1 0 opt-test test-single
+: tail-test ( n -- 0 ) dup if 1 - recurse then ;        \ a tail call, so it doesn't nest
+0 100000 tail-test test-single
+: throw-test ( n -- n ) dup 0< if 7 throw then ;
+5 0 5 ' throw-test catch test-dual
+-1 7 -1 ' throw-test catch test-dual
 
 ."        Application tests" cr
 1 0 fac test-single
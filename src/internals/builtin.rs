@@ -1,20 +1,30 @@
 /// Interpreter for builtins
 ///
 /// Set up a table of builtin functions, with names and code
-
-#[allow(dead_code)]
-use crate::engine::{BUILTIN_MASK, FALSE, STR_START, TF, TIB_START, VARIABLE};
-use crate::engine::{PAD_START, TMP_START};
+use crate::engine::{BUILTIN_MASK, FALSE, MAX_ORDER, STR_START, TF, TIB_START, VARIABLE, WORD_START};
+use crate::engine::{HOLD_END, PAD_START, TMP_START};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 // The mechanism for storing and calling function pointers
 pub trait BuiltinCall {
     fn call(&mut self);
 }
 
+/// A closure registered by a host program. It is shared so that it can be called while the
+/// builtins table is borrowed, and guarded by a RefCell so that it can hold mutable state.
+pub type BuiltinClosure = Rc<RefCell<Box<dyn FnMut(&mut TF)>>>;
+
+// The code for a builtin: either one of the engine's own functions, or a host closure
+pub enum BuiltinCode {
+    Function(for<'a> fn(&'a mut TF)),
+    Closure(BuiltinClosure),
+}
+
 // The internal format for builtins: a name, code pointer, and documentation string for use by SEE
 pub struct BuiltInFn {
     pub name: String,
-    pub code: for<'a> fn(&'a mut TF),
+    pub code: BuiltinCode,
     pub doc: String,
 }
 
@@ -24,7 +34,15 @@ impl BuiltinCall for BuiltInFn {
 
 impl BuiltInFn {
     pub fn new(name: String, code: for<'a> fn(&'a mut TF), doc: String) -> BuiltInFn {
-        BuiltInFn { name, code, doc }
+        BuiltInFn { name, code: BuiltinCode::Function(code), doc }
+    }
+
+    pub fn new_closure(name: String, code: Box<dyn FnMut(&mut TF)>, doc: String) -> BuiltInFn {
+        BuiltInFn {
+            name,
+            code: BuiltinCode::Closure(Rc::new(RefCell::new(code))),
+            doc,
+        }
     }
 }
 
@@ -36,32 +54,33 @@ impl TF {
     pub fn u_insert_variables(&mut self) {
         // install system variables in data area
         // hand craft S-HERE (free string pointer) so write_string() can work
-        self.data[0] = 0;
-        self.data[1] = 0;
-        self.data[2] = STR_START as i64; //
+        let w = WORD_START;
+        self.data[w] = 0;
+        self.data[w + 1] = 0; // the first back pointer, which ends the chain
+        self.data[w + 2] = STR_START as i64; //
         self.strings[STR_START] = 6 as char; // length of "s-here"
         for (i, c) in "s-here".chars().enumerate() {
             self.strings[i + STR_START + 1] = c;
         }
-        self.string_ptr = 4;
-        self.data[3] = VARIABLE;
-        self.data[4] = (STR_START + 7) as i64; // update the value of S-HERE
-        self.data[5] = 1; // back pointer
+        self.string_ptr = w + 4;
+        self.data[w + 3] = VARIABLE;
+        self.data[w + 4] = (STR_START + 7) as i64; // update the value of S-HERE
+        self.data[w + 5] = (w + 1) as i64; // back pointer
                           // hand craft HERE, because it's needed by make_word
         let name_pointer = self.u_new_string("here");
-        self.data[6] = name_pointer as i64;
-        self.data[7] = VARIABLE;
-        self.data[8] = 10; // the value of HERE
-        self.data[9] = 5; // back pointer
-        self.here_ptr = 8; // the address of the HERE variable
+        self.data[w + 6] = name_pointer as i64;
+        self.data[w + 7] = VARIABLE;
+        self.data[w + 8] = (w + 10) as i64; // the value of HERE
+        self.data[w + 9] = (w + 5) as i64; // back pointer
+        self.here_ptr = w + 8; // the address of the HERE variable
 
         // hand craft CONTEXT, because it's needed by make_word
-        self.data[10] = self.u_new_string("context") as i64;
-        self.data[11] = VARIABLE;
-        self.data[12] = 10;
-        self.data[13] = 9; // back pointer
-        self.context_ptr = 12;
-        self.data[self.here_ptr] = 14;
+        self.data[w + 10] = self.u_new_string("context") as i64;
+        self.data[w + 11] = VARIABLE;
+        self.data[w + 12] = (w + 10) as i64;
+        self.data[w + 13] = (w + 9) as i64; // back pointer
+        self.context_ptr = w + 12;
+        self.data[self.here_ptr] = (w + 14) as i64;
 
         self.pad_ptr = self.u_make_variable("pad");
         self.data[self.pad_ptr] = PAD_START as i64;
@@ -70,17 +89,26 @@ impl TF {
         self.tmp_ptr = self.u_make_variable("tmp");
         self.data[self.tmp_ptr] = TMP_START as i64;
         self.tib_ptr = self.u_make_variable("'tib");
-        self.data[self.tib_ptr] = 0;
+        self.data[self.tib_ptr] = TIB_START as i64;
         self.tib_size_ptr = self.u_make_variable("#tib");
         self.data[self.tib_size_ptr] = 0;
         self.tib_in_ptr = self.u_make_variable(">in");
-        self.data[self.tib_in_ptr] = TIB_START as i64 + 1;
+        self.data[self.tib_in_ptr] = 1;
         self.hld_ptr = self.u_make_variable("hld");
+        self.data[self.hld_ptr] = HOLD_END as i64;
         self.last_ptr = self.u_make_variable("last"); // points to nfa of new definition
         self.state_ptr = self.u_make_variable("'eval");
         self.abort_ptr = self.u_make_variable("abort?");
         self.state_ptr = self.u_make_variable("state");
         self.stepper_ptr = self.u_make_variable("stepper"); // turns the stepper on or off
+        self.current_ptr = self.u_make_variable("current"); // new definitions go into the forth wordlist
+        self.wordlists_ptr = self.u_make_variable("#wordlists");
+        self.data[self.wordlists_ptr] = 1; // just the forth wordlist
+        self.overflow_ptr = self.u_make_variable("overflow-mode"); // zero, so arithmetic wraps
+        let mut order = [0; MAX_ORDER + 2];
+        order[0] = VARIABLE;
+        order[1] = 1; // the search order is the forth wordlist, followed by room to extend it
+        self.order_ptr = self.u_make_word("#order", &order) + 1;
         self.data[self.abort_ptr] = FALSE;
     }
 
@@ -90,23 +118,21 @@ impl TF {
         // self.u_interpret("2 2 + .");
     }
 
-    /// u_write_string writes a new string into the next empty space, updating the free space pointer
+    /// u_new_string writes a new string into the next empty space, updating the free space pointer
     fn u_new_string(&mut self, string: &str) -> usize {
-        // place a new str into string space and update the free pointer string_ptr
-        let mut ptr = self.data[self.string_ptr] as usize;
-        let result_ptr = ptr;
-        self.strings[ptr] = string.len() as u8 as char;
-        ptr += 1;
+        let Some(result_ptr) = self.u_string_alloc(string.len(), "cold start") else {
+            return 0;
+        };
+        self.strings[result_ptr] = string.len() as u8 as char;
         for (i, c) in string.chars().enumerate() {
-            self.strings[ptr + i] = c;
+            self.strings[result_ptr + 1 + i] = c;
         }
-        self.data[self.string_ptr] = (ptr + string.len()) as i64;
         result_ptr
     }
 
     /// make-variable creates a variable, returning the address of the variable's value
     fn u_make_variable(&mut self, name: &str) -> usize {
-        let code_ptr = self.u_make_word(&name, &[VARIABLE, 0]); // install the name
+        let code_ptr = self.u_make_word(name, &[VARIABLE, 0]); // install the name
         code_ptr + 1 // the location of the variable's value
     }
 
@@ -123,9 +149,10 @@ impl TF {
     ///     return pointer to first parameter field - the code field pointer or cfa
     ///
     fn u_make_word(&mut self, name: &str, args: &[i64]) -> usize {
+        self.u_close_open_word();
         let back = self.data[self.here_ptr] as usize - 1; // the top-of-stack back pointer's location
         let mut ptr = back + 1;
-        self.data[ptr] = self.u_new_string(name) as i64;
+        self.data[ptr] = self.u_new_string(name) as i64 | self.u_current_wordlist();
         for val in args {
             ptr += 1;
             self.data[ptr] = *val;
@@ -144,11 +171,18 @@ impl TF {
     ///     pointer is incorrect.
     ///
     fn u_add_builtin(&mut self, name: &str, code: for<'a> fn(&'a mut TF), doc: &str) {
-        self.builtins
-            .push(BuiltInFn::new(name.to_owned(), code, doc.to_string()));
+        self.u_install_builtin(BuiltInFn::new(name.to_owned(), code, doc.to_string()));
+    }
+
+    /// u_install_builtin adds a builtin record to the table and creates its dictionary entry
+    ///     Used for the engine's own builtins and for closures registered by a host program.
+    ///
+    pub(crate) fn u_install_builtin(&mut self, builtin: BuiltInFn) {
+        let name = builtin.name.clone();
+        self.builtins.push(builtin);
         // now build the DATA space record
         let cfa = (self.builtins.len() - 1) | BUILTIN_MASK;
-        self.u_make_word(name, &[cfa as i64]);
+        self.u_make_word(&name, &[cfa as i64]);
     }
 
     /// Set up all the words that are implemented in Rust
@@ -159,6 +193,11 @@ impl TF {
         self.u_add_builtin("*", TF::f_times, "* ( j k -- j-k ) Push  -k on the stack");
         self.u_add_builtin("/", TF::f_divide, "/ ( j k -- j/k ) Push j/k on the stack");
         self.u_add_builtin("mod", TF::f_mod, "mod ( j k -- j/k ) Push j%k on the stack");
+        self.u_add_builtin(
+            "/mod",
+            TF::f_slash_mod,
+            "/mod ( j k -- r q ) Push the remainder and quotient of j/k",
+        );
         self.u_add_builtin(
             "<",
             TF::f_less,
@@ -252,20 +291,63 @@ impl TF {
             TF::f_or,
             "or ( a b -- a | b ) Pop a and b, returning the logical or",
         );
+        self.u_add_builtin("xor", TF::f_xor, "xor ( a b -- a ^ b ) Pop a and b, returning the bitwise exclusive or");
+        self.u_add_builtin("invert", TF::f_invert, "invert ( a -- !a ) Inverts every bit of a");
+        self.u_add_builtin("lshift", TF::f_lshift, "lshift ( a u -- a<<u ) Shifts a left by u bits");
+        self.u_add_builtin("rshift", TF::f_rshift, "rshift ( a u -- a>>u ) Shifts a right by u bits, filling with zeros");
+        self.u_add_builtin(
+            "arshift",
+            TF::f_arshift,
+            "arshift ( n u -- n>>u ) Shifts n right by u bits, copying the sign bit",
+        );
+        self.u_add_builtin("2*", TF::f_2star, "2* ( n -- n*2 ) Shifts n left by one bit");
+        self.u_add_builtin("2/", TF::f_2slash, "2/ ( n -- n/2 ) Shifts n right by one bit, copying the sign bit");
+        self.u_add_builtin("u<", TF::f_u_less, "u< ( u1 u2 -- flag ) True if u1 < u2, comparing unsigned");
+        self.u_add_builtin("u>", TF::f_u_greater, "u> ( u1 u2 -- flag ) True if u1 > u2, comparing unsigned");
+        self.u_add_builtin("umin", TF::f_umin, "umin ( u1 u2 -- u ) The smaller of two unsigned numbers");
+        self.u_add_builtin("umax", TF::f_umax, "umax ( u1 u2 -- u ) The larger of two unsigned numbers");
+        self.u_add_builtin("within", TF::f_within, "within ( n lo hi -- flag ) True if lo <= n < hi");
         self.u_add_builtin("@", TF::f_get, "@: ( a -- v ) Pushes variable a's value");
         self.u_add_builtin("!", TF::f_store, "!: ( v a -- ) stores v at address a");
-        self.u_add_builtin("i", TF::f_i, "Pushes the current FOR - NEXT loop index");
+        self.u_add_builtin("i", TF::f_i, "Pushes the current loop index");
         self.u_add_builtin("j", TF::f_j, "Pushes the second-level (outer) loop index");
+        self.u_add_builtin("k", TF::f_k, "Pushes the third-level loop index");
+        self.u_add_builtin("unloop", TF::f_unloop, "Discards the current loop parameters, before exit");
         self.u_add_builtin(
             "abort",
             TF::f_abort,
             "abort ( -- ) Ends execution of the current word and clears the stack",
         );
+        self.u_add_builtin(
+            "catch",
+            TF::f_catch,
+            "catch ( i*x xt -- j*x 0 | i*x n ) Executes xt, returning 0, or the code n if xt throws",
+        );
+        self.u_add_builtin(
+            "throw",
+            TF::f_throw,
+            "throw ( k*x n -- k*x | i*x n ) If n is non-zero, returns to the most recent catch with n",
+        );
+        self.u_add_builtin(
+            "(abort\")",
+            TF::f_abort_quote_p,
+            "(abort\") ( s -- ) Prints the string at s and throws -2. Compiled by abort\"",
+        );
         self.u_add_builtin(
             "depth",
             TF::f_stack_depth,
             "depth: Pushes the current stack depth",
         );
+        self.u_add_builtin(
+            "unused",
+            TF::f_unused,
+            "unused ( -- u ) Pushes the number of cells that can still be allotted",
+        );
+        self.u_add_builtin(
+            "s-unused",
+            TF::f_s_unused,
+            "s-unused ( -- u ) Pushes the number of characters free in string space",
+        );
         self.u_add_builtin(
             "key",
             TF::f_key,
@@ -321,8 +403,8 @@ impl TF {
         self.u_add_builtin(
             "number?",
             TF::f_number_q,
-            "number? ( a -- n T | a F ) tests a string to see if it's a number;
-            leaves n and flag on the stack: true if number is ok.",
+            "number? ( a -- n T | d 2 | a F ) tests a string to see if it's a number;
+            leaves n and flag on the stack: true if number is ok, or 2 for a double such as 123.",
         );
         self.u_add_builtin(
             "?unique",
@@ -364,8 +446,119 @@ impl TF {
         self.u_add_builtin(
             "create",
             TF::f_create,
-            "create <name> ( -- ) creates a name field in the dictionary",
+            "create <name> ( -- ) creates a word that pushes the address of the data compiled after it",
+        );
+        self.u_add_builtin(
+            "(create)",
+            TF::f_create_header,
+            "(create) <name> ( -- ) creates a name field in the dictionary",
         );
+        self.u_add_builtin(
+            "(does>)",
+            TF::f_does_p,
+            "(does>) ( a -- ) runs the code at a when the most recently created word is executed",
+        );
+        self.u_add_builtin("wordlist", TF::f_wordlist, "wordlist ( -- wid ) creates a new, empty wordlist");
+        self.u_add_builtin(
+            "forth-wordlist",
+            TF::f_forth_wordlist,
+            "forth-wordlist ( -- wid ) the wordlist holding the builtins and the core library",
+        );
+        self.u_add_builtin(
+            "get-order",
+            TF::f_get_order,
+            "get-order ( -- widn .. wid1 n ) pushes the search order, with the first wordlist searched on top",
+        );
+        self.u_add_builtin(
+            "set-order",
+            TF::f_set_order,
+            "set-order ( widn .. wid1 n -- ) sets the search order, searching wid1 first. -1 set-order is only",
+        );
+        self.u_add_builtin(
+            "get-current",
+            TF::f_get_current,
+            "get-current ( -- wid ) pushes the wordlist new definitions are added to",
+        );
+        self.u_add_builtin(
+            "set-current",
+            TF::f_set_current,
+            "set-current ( wid -- ) makes new definitions go into wordlist wid",
+        );
+        self.u_add_builtin("only", TF::f_only, "only ( -- ) sets the search order to just the forth wordlist");
+        self.u_add_builtin("also", TF::f_also, "also ( -- ) duplicates the first wordlist in the search order");
+        self.u_add_builtin("previous", TF::f_previous, "previous ( -- ) removes the first wordlist from the search order");
+        self.u_add_builtin(
+            "definitions",
+            TF::f_definitions,
+            "definitions ( -- ) makes new definitions go into the first wordlist in the search order",
+        );
+        self.u_add_builtin("order", TF::f_order, "order ( -- ) prints the search order and the current wordlist");
+        self.u_add_builtin("s>d", TF::f_s_to_d, "s>d ( n -- d ) Converts a number to a double-cell number");
+        self.u_add_builtin("m*", TF::f_m_star, "m* ( n1 n2 -- d ) Multiplies, giving a double-cell product");
+        self.u_add_builtin(
+            "um*",
+            TF::f_um_star,
+            "um* ( u1 u2 -- ud ) Multiplies unsigned numbers, giving an unsigned double-cell product",
+        );
+        self.u_add_builtin(
+            "um/mod",
+            TF::f_um_slash_mod,
+            "um/mod ( ud u1 -- u2 u3 ) Divides an unsigned double by u1, giving remainder u2 and quotient u3",
+        );
+        self.u_add_builtin(
+            "sm/rem",
+            TF::f_sm_slash_rem,
+            "sm/rem ( d n -- rem quot ) Divides a double by n, rounding the quotient towards zero",
+        );
+        self.u_add_builtin(
+            "fm/mod",
+            TF::f_fm_slash_mod,
+            "fm/mod ( d n -- rem quot ) Divides a double by n, rounding the quotient towards negative infinity",
+        );
+        self.u_add_builtin(
+            "*/",
+            TF::f_star_slash,
+            "*/ ( n1 n2 n3 -- n4 ) Multiplies n1 by n2, then divides by n3, with a double-cell intermediate product",
+        );
+        self.u_add_builtin(
+            "*/mod",
+            TF::f_star_slash_mod,
+            "*/mod ( n1 n2 n3 -- rem quot ) As */, but leaving the remainder as well",
+        );
+        self.u_add_builtin("d+", TF::f_d_plus, "d+ ( d1 d2 -- d3 ) Adds double-cell numbers");
+        self.u_add_builtin("d-", TF::f_d_minus, "d- ( d1 d2 -- d3 ) Subtracts d2 from d1");
+        self.u_add_builtin("dnegate", TF::f_dnegate, "dnegate ( d -- -d ) Negates a double-cell number");
+        self.u_add_builtin("d<", TF::f_d_less, "d< ( d1 d2 -- flag ) True if d1 is less than d2");
+        self.u_add_builtin("<#", TF::f_less_number, "<# ( -- ) Starts a pictured numeric output string");
+        self.u_add_builtin("#", TF::f_number_sign, "# ( ud1 -- ud2 ) Adds the next digit of ud1 to the string");
+        self.u_add_builtin("#s", TF::f_number_sign_s, "#s ( ud -- 0 0 ) Adds all the remaining digits to the string");
+        self.u_add_builtin("hold", TF::f_hold, "hold ( c -- ) Adds a character to the string");
+        self.u_add_builtin("holds", TF::f_holds, "holds ( s u -- ) Adds a string to the string");
+        self.u_add_builtin("sign", TF::f_sign, "sign ( n -- ) Adds a minus sign to the string if n is negative");
+        self.u_add_builtin("#>", TF::f_number_greater, "#> ( xd -- s u ) Ends the string, leaving its address and length");
+        self.u_add_builtin("f+", TF::f_f_plus, "f+ ( F: r1 r2 -- r3 ) Adds the top two floats");
+        self.u_add_builtin("f-", TF::f_f_minus, "f- ( F: r1 r2 -- r3 ) Subtracts r2 from r1");
+        self.u_add_builtin("f*", TF::f_f_times, "f* ( F: r1 r2 -- r3 ) Multiplies the top two floats");
+        self.u_add_builtin("f/", TF::f_f_divide, "f/ ( F: r1 r2 -- r3 ) Divides r1 by r2");
+        self.u_add_builtin("f.", TF::f_f_dot, "f. ( F: r -- ) Prints the top of the float stack");
+        self.u_add_builtin("f@", TF::f_f_fetch, "f@ ( a -- ) ( F: -- r ) Fetches the float stored at address a");
+        self.u_add_builtin("f!", TF::f_f_store, "f! ( a -- ) ( F: r -- ) Stores a float at address a");
+        self.u_add_builtin("fdup", TF::f_fdup, "fdup ( F: r -- r r ) Duplicates the top of the float stack");
+        self.u_add_builtin("fswap", TF::f_fswap, "fswap ( F: r1 r2 -- r2 r1 ) Swaps the top two floats");
+        self.u_add_builtin("fdrop", TF::f_fdrop, "fdrop ( F: r -- ) Drops the top of the float stack");
+        self.u_add_builtin("f<", TF::f_f_less, "f< ( -- flag ) ( F: r1 r2 -- ) True if r1 is less than r2");
+        self.u_add_builtin("f0=", TF::f_f_zero_equal, "f0= ( -- flag ) ( F: r -- ) True if r is zero");
+        self.u_add_builtin("s>f", TF::f_s_to_f, "s>f ( n -- ) ( F: -- r ) Converts an integer to a float");
+        self.u_add_builtin(
+            "f>s",
+            TF::f_f_to_s,
+            "f>s ( -- n ) ( F: r -- ) Converts a float to an integer, truncating towards zero",
+        );
+        self.u_add_builtin("fsqrt", TF::f_fsqrt, "fsqrt ( F: r1 -- r2 ) Square root");
+        self.u_add_builtin("fsin", TF::f_fsin, "fsin ( F: r1 -- r2 ) Sine of r1, in radians");
+        self.u_add_builtin("fcos", TF::f_fcos, "fcos ( F: r1 -- r2 ) Cosine of r1, in radians");
+        self.u_add_builtin("fexp", TF::f_fexp, "fexp ( F: r1 -- r2 ) e raised to the power r1");
+        self.u_add_builtin("fln", TF::f_fln, "fln ( F: r1 -- r2 ) Natural logarithm of r1");
         self.u_add_builtin(
             "s-move",
             TF::f_smove,
@@ -401,17 +594,54 @@ impl TF {
         self.u_add_builtin(
             "s-copy",
             TF::f_s_copy,
-            "s-copy ( source dest -- ) Copy a counted string from source to dest",
+            "s-copy ( source dest -- dest ) Copy a counted string from source to dest",
+        );
+        self.u_add_builtin(
+            "s-transient",
+            TF::f_s_transient,
+            "s-transient ( s u -- s' u ) Copy a counted string into the ring of transient strings used by s\"",
         );
         self.u_add_builtin(
             "c@",
             TF::f_c_get,
-            "c@ ( s -- c ) Copy a character from string address s to the stack",
+            "c@ ( addr -- c ) Copy a character from addr, in string space or data space, to the stack",
         );
         self.u_add_builtin(
             "c!",
             TF::f_c_store,
-            "c! ( c s -- ) Copy character c to string address s",
+            "c! ( c addr -- ) Copy character c to addr, in string space or data space",
+        );
+        self.u_add_builtin(
+            "allot",
+            TF::f_allot,
+            "allot ( n -- ) Reserve n cells of data space, or give them back if n is negative",
+        );
+        self.u_add_builtin(
+            "fill",
+            TF::f_fill,
+            "fill ( addr u c -- ) Store character c in u addresses starting at addr",
+        );
+        self.u_add_builtin("erase", TF::f_erase, "erase ( addr u -- ) Store zero in u addresses starting at addr");
+        self.u_add_builtin(
+            "allocate",
+            TF::f_allocate,
+            "allocate ( u -- a-addr ior ) Allocate u cells from the heap. ior is 0, or -59 if there isn't room",
+        );
+        self.u_add_builtin("free", TF::f_free, "free ( a-addr -- ior ) Return a block to the heap. ior is 0 or -60");
+        self.u_add_builtin(
+            "resize",
+            TF::f_resize,
+            "resize ( a-addr1 u -- a-addr2 ior ) Change the size of a block, moving it if necessary. ior is 0 or -61",
+        );
+        self.u_add_builtin(
+            "heap-stats",
+            TF::f_heap_stats,
+            "heap-stats ( -- ) List the blocks allocated from the heap, the free space and its fragmentation",
+        );
+        self.u_add_builtin(
+            "move",
+            TF::f_move,
+            "move ( from to u -- ) Copy u addresses from one area to another, which may overlap",
         );
         self.u_add_builtin("now", TF::f_now, "c! ( -- ) Start a timers");
         self.u_add_builtin(
@@ -435,6 +665,10 @@ impl TF {
         self.u_add_builtin("file-size", TF::f_file_size, "file-size ( file-id -- u ior ) Returns the size in characters of the file, plus an i/o result code");
         self.u_add_builtin("(system)", TF::f_system_p, "(system) ( s -- ) Execute a shell command, using string s.
         Output is channeled to stdout");
+        self.u_add_builtin("(save-image)", TF::f_save_image_p, "(save-image) ( s -- ) Save the dictionary to the file named by string s.
+        Load it again with the --image option");
+        self.u_add_builtin("(turnkey)", TF::f_turnkey_p, "(turnkey) ( s1 s2 -- ) Write an executable to the file named by s1,
+        which runs the word named by s2 instead of the interpreter");
         self.u_add_builtin("ms", TF::f_ms, "sleep ( ms -- ) Puts the current thread to sleep for ms milliseconds");
     }
 }
@@ -1,41 +1,24 @@
 // Compiler and Interpreter
 
 use crate::engine::{
-    ABORT, ADDRESS_MASK, BRANCH, BRANCH0, BUILTIN, BUILTIN_MASK, CONSTANT, DEFINITION, EXIT, FALSE,
-    IMMEDIATE_MASK, LITERAL, BREAK, STACK_START, STRLIT, TF, TRUE, VARIABLE,
+    ABORT, ADDRESS_MASK, BRANCH, BRANCH0, BUILTIN, BUILTIN_MASK, CONSTANT, DEFINITION, ERR_ABORT, ERR_DICTIONARY_OVERFLOW,
+    ERR_INVALID_ADDRESS, ERR_INVALID_ARGUMENT, ERR_OUT_OF_RANGE, ERR_STACK_OVERFLOW, ERR_STACK_UNDERFLOW, ERR_UNDEFINED_WORD, EXIT, FALSE,
+    IMMEDIATE_MASK, LITERAL, BREAK, STRLIT, TF, TRUE, VARIABLE, DO, QDO, LOOP, PLUS_LOOP, LEAVE, DOES,
+    FLITERAL, ERR_UNSUPPORTED, WORDLIST_MASK, LIT_PLUS, LIT_MINUS, DUP_BRANCH0, OVER_OVER, R_FROM_DROP, STACK_ROOM,
 };
-use crate::internals::general::u_is_integer;
+use crate::utility::{float_literal, parse_number, Number};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
 
-macro_rules! stack_ok {
-    ($self:ident, $n: expr, $caller: expr) => {
-        if $self.stack_ptr <= STACK_START - $n {
-            // $self.f_dot_s();
-            true
-        } else {
-            $self.msg.error($caller, "Stack underflow", None::<bool>);
-            $self.f_abort();
-            false
-        }
-    };
-}
-macro_rules! pop {
-    ($self:ident) => {{
-        let r = $self.data[$self.stack_ptr];
-        //$self.data[$self.stack_ptr] = 999999;
-        $self.stack_ptr += 1;
-        r
-    }};
-}
-macro_rules! top {
-    ($self:ident) => {{
-        $self.data[$self.stack_ptr]
-    }};
-}
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
+/// HeaderMark records the dictionary pointers from before a new header, so an aborted definition can be undone
+///
+#[derive(Clone, Copy)]
+pub struct HeaderMark {
+    here: i64,
+    last: i64,
+    context: i64,
+    s_here: i64,
+    open_word: usize,
 }
 
 impl TF {
@@ -43,17 +26,21 @@ impl TF {
     ///     Context pointer links to the most recent name field
     ///
     pub fn f_immediate(&mut self) {
-        let mut str_addr = self.data[self.data[self.context_ptr] as usize] as usize;
+        let nfa = self.data[self.context_ptr] as usize;
+        let mut str_addr = self.data[nfa] as usize;
         str_addr |= IMMEDIATE_MASK;
-        self.data[self.data[self.context_ptr] as usize] = str_addr as i64;
+        self.data[nfa] = str_addr as i64;
     }
 
     /// immediate? ( cfa -- T | F ) Determines if a word is immediate or not
     ///
     pub fn f_immediate_q(&mut self) {
         if stack_ok!(self, 1, "immediate?") {
-            let cfa = pop!(self) as usize;
-            let name_ptr = self.data[cfa - 1] as usize;
+            let cfa = pop!(self);
+            if !data_ok!(self, cfa - 1, "immediate?") {
+                return;
+            }
+            let name_ptr = self.data[cfa as usize - 1] as usize;
             let immed = name_ptr & IMMEDIATE_MASK;
             let result = if immed == 0 { FALSE } else { TRUE };
             push!(self, result);
@@ -61,27 +48,60 @@ impl TF {
     }
 
     /// abort empties the stack, resets any pending operations, and returns to the prompt
+    ///     abort is THROW -1: unless it is caught, the outer interpreter clears the stacks.
     ///     There is a version called abort" implemented in Forth, which prints an error message
     ///
     pub fn f_abort(&mut self) {
         // empty the stack, reset any pending operations, and return to the prompt
-        self.msg
-            .warning("ABORT", "Terminating execution", None::<bool>);
-        self.f_clear();
-        self.set_abort_flag(true);
+        if self.catch_depth == 0 {
+            self.msg
+                .warning("ABORT", "Terminating execution", None::<bool>);
+        }
+        self.u_throw(ERR_ABORT);
+    }
+
+    /// u_invalid_address reports an out of range memory access and throws
+    ///
+    pub fn u_invalid_address(&mut self, caller: &str, addr: i64) {
+        self.u_error(caller, ERR_INVALID_ADDRESS, Some(addr));
+    }
+
+    /// u_stack_underflow is called by pop! when the stack is empty
+    ///     Only the first underflow is reported: once thrown, the remaining pops on the way out are quiet.
+    ///
+    pub fn u_stack_underflow(&mut self) {
+        self.u_error("pop", ERR_STACK_UNDERFLOW, None::<bool>);
+    }
+
+    /// u_stack_overflow is called by push! when there is no room left for the stack
+    ///     The error names the builtin that was running, since that is the word that pushed.
+    ///
+    pub fn u_stack_overflow(&mut self) {
+        let name = match self.builtins.get(self.current_builtin) {
+            Some(builtin) => builtin.name.clone(),
+            None => "push".to_owned(),
+        };
+        self.u_error(&name, ERR_STACK_OVERFLOW, None::<bool>);
     }
 
     /// quit is the main loop in Forth, reading from the input stream and dispatching for evaluation
     ///     quit also issues the prompt and checks for a shutdown (exit) condition
+    ///     Each line is run inside catch_unwind, so a panic in the engine loses the line, not the session.
     pub fn f_quit(&mut self) {
-        self.f_abort();
+        self.f_clear();
         loop {
             if self.should_exit() {
                 break;
             } else {
                 self.set_abort_flag(false);
-                self.f_query();
-                self.f_eval(); // interpret the contents of the line
+                let line = panic::catch_unwind(AssertUnwindSafe(|| {
+                    self.f_query();
+                    self.f_eval(); // interpret the contents of the line
+                }));
+                if let Err(cause) = line {
+                    self.u_recover(cause);
+                }
+                self.u_clear_exception(1); // an uncaught exception abandons any files being included
                 if self.reader.len() == 1 {
                     if self.show_stack {
                         self.f_dot_s();
@@ -93,15 +113,44 @@ impl TF {
         }
     }
 
+    /// u_recover reports a panic caught by quit, and arranges for the stacks and input to be reset
+    ///     The dictionary is left as it was, so definitions made earlier in the session survive.
+    ///
+    fn u_recover(&mut self, cause: Box<dyn Any + Send>) {
+        let reason = match cause.downcast_ref::<&str>() {
+            Some(s) => s.to_string(),
+            None => match cause.downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => "unknown cause".to_string(),
+            },
+        };
+        self.msg.error("quit", "Internal error, stacks reset", Some(reason));
+        self.catch_depth = 0; // any CATCH frames were lost in the unwind
+        self.set_abort_flag(false);
+        self.u_throw(ERR_ABORT); // u_clear_exception does the reset
+    }
+
     /// EXECUTE ( cfa -- ) interpret a word with addr on the stack
     /// stack value is the address of an inner interpreter
     pub fn f_execute(&mut self) {
         if stack_ok!(self, 1, "execute") {
             // call the appropriate inner interpreter
             let xt = pop!(self);
+            if xt as usize & BUILTIN_MASK != 0 {
+                // ' returns the compiled form of a builtin: the flagged index, not a cfa
+                push!(self, (xt as usize & ADDRESS_MASK) as i64);
+                self.i_builtin();
+                return;
+            }
+            if !data_ok!(self, xt, "execute") || !data_ok!(self, xt + 1, "execute") {
+                return;
+            }
             push!(self, xt + 1);
             match self.data[xt as usize] {
-                BUILTIN => self.msg.error("f_execute", "BUILTIN found", Some(xt)), //self.i_builtin(),
+                BUILTIN => {
+                    pop!(self); // a bare BUILTIN opcode isn't a word
+                    self.u_invalid_address("execute", xt);
+                }
                 VARIABLE => self.i_variable(),
                 CONSTANT => self.i_constant(),
                 LITERAL => self.i_literal(),
@@ -112,9 +161,14 @@ impl TF {
                 ABORT => self.i_abort(),
                 EXIT => self.i_exit(),
                 BREAK => self.i_exit(),
+                DOES => self.i_does(),
                 _ => {
                     pop!(self);
                     let cfa = self.data[xt as usize] as usize & ADDRESS_MASK;
+                    if cfa >= self.builtins.len() {
+                        self.u_invalid_address("execute", xt);
+                        return;
+                    }
                     push!(self, cfa as i64);
                     self.i_builtin();
                 }
@@ -125,6 +179,9 @@ impl TF {
     /// EVAL ( -- ) Interprets a line of tokens from the Text Input Buffer (TIB
     pub fn f_eval(&mut self) {
         loop {
+            if self.get_abort_flag() {
+                break; // abandon the rest of the line
+            }
             push!(self, self.data[self.pad_ptr]);
             push!(self, ' ' as i64);
             self.f_parse_to(); //  ( -- b u ) get a token
@@ -146,7 +203,7 @@ impl TF {
 
     /// $COMPILE ( s -- ) compiles a token whose string address is on the stack
     ///            If not a word, try to convert to a number
-    ///            If not a number, ABORT.
+    ///            If not a number, THROW -13 (undefined word).
     pub fn f_d_compile(&mut self) {
         if stack_ok!(self, 1, "$compile") {
             self.f_find();
@@ -157,7 +214,7 @@ impl TF {
                 self.f_immediate_q();
                 if pop!(self) == TRUE {
                     // call the interpreter for this word
-                    push!(self, self.data[self.pad_ptr] as i64);
+                    push!(self, self.data[self.pad_ptr]);
                     self.f_d_interpret();
                 } else {
                     // check if it's a builtin, and compile appropriately
@@ -171,14 +228,22 @@ impl TF {
                 }
             } else {
                 self.f_number_q();
-                if pop!(self) == TRUE {
+                let flag = pop!(self);
+                if flag == TRUE {
                     self.f_literal(); // compile the literal
+                } else if flag == 2 {
+                    // a double compiles as two literals, low cell first
+                    let high = pop!(self);
+                    self.f_literal();
+                    push!(self, high);
+                    self.f_literal();
+                } else if let Some(r) = self.u_float_q() {
+                    pop!(self); // lose the failed number
+                    self.u_fliteral(r);
                 } else {
                     pop!(self); // lose the failed number
-                    let word = &self.u_get_string(self.data[self.pad_ptr] as usize);
-                    self.msg
-                        .warning("$interpret", "token not recognized", Some(word));
-                    self.f_abort();
+                    let word = self.u_get_string(self.data[self.pad_ptr] as usize);
+                    self.u_error("$compile", ERR_UNDEFINED_WORD, Some(word));
                 }
             }
         }
@@ -186,7 +251,7 @@ impl TF {
 
     /// $INTERPRET ( s -- ) executes a token whose string address is on the stack.
     ///            If not a word, try to convert to a number
-    ///            If not a number, ABORT.
+    ///            If not a number, THROW -13 (undefined word).
     ///
     pub fn f_d_interpret(&mut self) {
         if stack_ok!(self, 1, "$interpret") {
@@ -197,42 +262,37 @@ impl TF {
                 self.f_execute();
             } else {
                 // try number?
-                self.f_number_q(); // ( s -- n T | a F )
-                if pop!(self) == TRUE {
+                self.f_number_q(); // ( s -- n T | d 2 | a F )
+                if pop!(self) != FALSE {
                     // leave the converted number on the stack
+                } else if let Some(r) = self.u_float_q() {
+                    pop!(self); // lose the failed number
+                    self.u_fpush(r, "$interpret");
                 } else {
                     pop!(self); // lose the failed number
-                    let word = &self.u_get_string(token_addr as usize);
-                    self.msg
-                        .warning("$interpret", "token not recognized", Some(word));
+                    let word = self.u_get_string(token_addr as usize);
+                    self.u_error("$interpret", ERR_UNDEFINED_WORD, Some(word));
                 }
             }
         }
     }
 
-    /// FIND (s -- cfa T | s F ) Search the dictionary for the token indexed through s.
+    /// FIND (s -- cfa T | s F ) Search the wordlists in the search order for the token indexed through s.
     ///     If not found, return the string address so NUMBER? can look at it
     ///
     pub fn f_find(&mut self) {
         if stack_ok!(self, 1, "find") {
-            let mut result = false;
             let source_addr = pop!(self) as usize;
-            let mut link = self.data[self.context_ptr] as usize - 1;
-            // link = self.data[link] as usize; // go back to the beginning of the top word
-            while link > 0 {
-                // name field is immediately after the link
-                let nfa_val = self.data[link + 1];
-                let str_addr = nfa_val as usize & ADDRESS_MASK;
-                if self.strings[str_addr] as u8 == self.strings[source_addr] as u8 {
-                    if self.u_str_equal(source_addr, str_addr as usize) {
-                        result = true;
-                        break;
-                    }
-                }
-                link = self.data[link] as usize;
+            if !string_ok!(self, source_addr, 1, "find")
+                || !string_ok!(self, source_addr, self.strings[source_addr] as usize + 1, "find")
+            {
+                return;
             }
-            if result {
-                push!(self, link as i64 + 2);
+            let length = self.strings[source_addr] as usize;
+            let name: String = self.strings[source_addr + 1..source_addr + 1 + length].iter().collect();
+            let found = self.u_search(&name);
+            if let Some(cfa) = found {
+                push!(self, cfa as i64);
                 push!(self, TRUE);
             } else {
                 push!(self, source_addr as i64);
@@ -243,27 +303,68 @@ impl TF {
         }
     }
 
-    /// number? ( s -- n T | a F ) tests a string to see if it's a number;
+    /// number? ( s -- n T | d 2 | a F ) tests a string to see if it's a number;
     /// leaves n and flag on the stack: true if number is ok.
+    ///     Numbers are read in the current BASE, unless they start with a # $ or % prefix.
+    ///     A number ending in a decimal point, such as 123., is a double-cell number, flagged with 2.
+    ///     Throws -11 if the number is too large, and -24 if BASE is not between 2 and 36.
     ///
     pub fn f_number_q(&mut self) {
         let buf_addr = pop!(self);
         let numtext = self.u_get_string(buf_addr as usize);
-        if u_is_integer(&numtext.as_str()) {
-            let result = numtext.parse().unwrap();
-            push!(self, result);
-            push!(self, TRUE);
+        if self.get_abort_flag() {
+            return;
+        }
+        let base = self.data[self.base_ptr];
+        let number = if (2..=36).contains(&base) {
+            parse_number(&numtext, base as u32)
         } else {
-            push!(self, buf_addr);
-            push!(self, FALSE);
+            self.u_error("number?", ERR_INVALID_ARGUMENT, Some(base));
+            Number::Invalid
+        };
+        match number {
+            Number::Single(n) => {
+                push!(self, n);
+                push!(self, TRUE);
+            }
+            Number::Double(d) => {
+                self.u_push_double(d);
+                push!(self, 2);
+            }
+            Number::Overflow => {
+                self.u_error("number?", ERR_OUT_OF_RANGE, Some(numtext));
+                push!(self, buf_addr);
+                push!(self, FALSE);
+            }
+            Number::Invalid => {
+                push!(self, buf_addr);
+                push!(self, FALSE);
+            }
+        }
+    }
+
+    /// u_float_q converts the token left on the stack by a failed NUMBER? to a float, if it is a float literal
+    ///     Float literals are only recognized when BASE is decimal, as in hex 1E0 is an integer.
+    ///
+    fn u_float_q(&mut self) -> Option<f64> {
+        if self.data[self.base_ptr] != 10 {
+            return None;
         }
+        let token = self.u_get_string(top!(self) as usize);
+        float_literal(&token)
     }
 
     /// f_comma ( n -- ) compile a value into a definition
     ///     Takes the top of the stack and writes it to the next free location in data space
+    ///     Throws -8 if the dictionary has grown up to the stack, leaving the stack STACK_ROOM cells.
     pub fn f_comma(&mut self) {
-        self.data[self.data[self.here_ptr] as usize] = pop!(self);
-        self.data[self.here_ptr] += 1;
+        let here = self.data[self.here_ptr];
+        if here + STACK_ROOM as i64 >= self.stack_ptr as i64 {
+            self.u_error(",", ERR_DICTIONARY_OVERFLOW, None::<bool>);
+        } else if data_ok!(self, here, ",") {
+            self.data[here as usize] = pop!(self);
+            self.data[self.here_ptr] += 1;
+        }
     }
 
     /// f_literal ( n -- ) compile a literal number with it's inner interpreter code pointer
@@ -277,14 +378,17 @@ impl TF {
     }
 
     /// UNIQUE? (s -- s )
-    ///     Checks the dictionary to see if the word pointed to is defined.
+    ///     Checks the dictionary to see if the word pointed to is defined in the current wordlist.
+    ///     Hiding a word in another wordlist is what wordlists are for, so it isn't reported.
     ///     No stack impact - it's just offering a warning.
     pub fn f_q_unique(&mut self) {
         self.f_dup();
         self.f_find();
         let result = pop!(self);
-        pop!(self);
-        if result == TRUE {
+        let cfa = pop!(self);
+        if result == TRUE
+            && self.data[cfa as usize - 1] as usize & WORDLIST_MASK == self.u_current_wordlist() as usize
+        {
             self.msg
                 .warning("unique?", "Overwriting existing definition", None::<bool>);
         }
@@ -326,9 +430,12 @@ impl TF {
             // traverse the string, dropping leading delim characters
             // in_p points *into* a string, so no count field
             if buf_len > 0 {
+                if !string_ok!(self, in_p, buf_len, "(parse)") {
+                    return;
+                }
                 let start = in_p as usize;
                 let end = start + buf_len as usize;
-                let mut i = start as usize;
+                let mut i = start;
                 let mut j;
                 while self.strings[i] == delim && i < end {
                     i += 1;
@@ -364,7 +471,6 @@ impl TF {
                 self.data[self.tib_size_ptr] = 0;
                 push!(self, self.data[self.tib_in_ptr]);
                 push!(self, 0); // indicates nothing found, TIB is empty
-                return;
             } else {
                 push!(
                     // starting address in the string
@@ -384,6 +490,9 @@ impl TF {
                 let addr = pop!(self);
                 if length > 0 {
                     // copy to pad
+                    if !string_ok!(self, dest, length + 1, "parse-to") {
+                        return;
+                    }
                     self.u_str_copy(
                         (addr + delta) as usize,
                         dest as usize,
@@ -405,44 +514,129 @@ impl TF {
     ///
     pub fn f_colon(&mut self) {
         self.set_compile_mode(true);
-        self.f_create(); // gets the name and makes a new dictionary entry
+        self.u_mark_header();
+        self.f_create_header(); // gets the name and makes a new dictionary entry
         push!(self, DEFINITION);
         self.f_comma();
     }
 
     /// ; terminates a definition, writing the cfa for EXIT, and resetting to interpret mode
-    ///     It has to write the exit code word, and add a back pointer
+    ///     It has to write the exit code word, run the optimizer over the definition, and add a back pointer
     ///     It also has to update HERE and CONTEXT.
     ///     Finally it switches out of compile mode
     ///
     pub fn f_semicolon(&mut self) {
         push!(self, EXIT);
         self.f_comma();
-        self.data[self.data[self.here_ptr] as usize] = self.data[self.last_ptr] - 1; // write the back pointer
+        let start = self.data[self.last_ptr] as usize + 2; // after the name field and the DEFINITION opcode
+        let here = self.u_optimize(start, self.data[self.here_ptr] as usize);
+        self.data[self.here_ptr] = here as i64;
+        self.data[here] = self.data[self.last_ptr] - 1; // write the back pointer
         self.data[self.here_ptr] += 1; // over EXIT and back pointer
         self.data[self.context_ptr] = self.data[self.last_ptr]; // adds the new definition to FIND
         self.set_compile_mode(false);
+        self.header_mark = None;
     }
 
-    /// CREATE <name> ( -- ) makes a new dictionary entry, using a postfix name
-    ///     References HERE, and assumes back pointer is in place already
-    ///     create updates the three definition-related pointers: HERE, CONTEXT and LAST
+    /// CREATE <name> ( -- ) makes a word that pushes the address of its data field
+    ///     The data is compiled after it with , and the word can be given other behavior with DOES>.
+    ///     The word can be found at once, but its back pointer isn't written until the next word is defined,
+    ///     so that the data can grow.
+    ///
     pub fn f_create(&mut self) {
+        self.u_mark_header();
+        self.f_create_header();
+        push!(self, DOES);
+        self.f_comma();
+        push!(self, 0); // no DOES> code yet
+        self.f_comma();
+        let nfa = self.data[self.last_ptr];
+        self.data[self.context_ptr] = nfa; // adds the new word to FIND
+        self.open_word = nfa as usize;
+        if !self.get_compile_mode() {
+            self.header_mark = None; // made outside a definition, the word is already complete
+        }
+    }
+
+    /// u_mark_header saves the dictionary pointers before : or CREATE adds a header
+    ///
+    fn u_mark_header(&mut self) {
+        self.header_mark = Some(HeaderMark {
+            here: self.data[self.here_ptr],
+            last: self.data[self.last_ptr],
+            context: self.data[self.context_ptr],
+            s_here: self.data[self.string_ptr],
+            open_word: self.open_word,
+        });
+    }
+
+    /// u_undo_header removes a definition abandoned by an uncaught exception, as if it had never been started
+    ///     Without this, the half-built header would stay in the dictionary with no back pointer after it,
+    ///     and the next word would be linked into the middle of its code.
+    ///
+    pub fn u_undo_header(&mut self) {
+        if let Some(mark) = self.header_mark.take() {
+            self.data[self.here_ptr] = mark.here;
+            self.data[self.last_ptr] = mark.last;
+            self.data[self.context_ptr] = mark.context;
+            self.data[self.string_ptr] = mark.s_here;
+            self.open_word = mark.open_word;
+        }
+    }
+
+    /// (create) <name> ( -- ) makes a new dictionary header, using a postfix name
+    ///     References HERE, and assumes back pointer is in place already
+    ///     (create) updates LAST and HERE. The caller writes the code field and the back pointer.
+    pub fn f_create_header(&mut self) {
+        self.u_close_open_word();
         push!(self, self.data[self.pad_ptr]);
         push!(self, ' ' as i64);
         self.f_parse_to(); // get the word's name
         pop!(self); // throw away the length, keep the text pointer
         self.f_q_unique(); // issue a warning if it's already defined
-        let length = self.strings[self.data[self.pad_ptr] as usize] as u8 as i64;
-        push!(self, length);
-        push!(self, self.data[self.string_ptr]);
+        let length = self.strings[self.data[self.pad_ptr] as usize] as u8 as usize;
+        let Some(dest) = self.u_string_alloc(length, "create") else {
+            pop!(self);
+            return;
+        };
+        push!(self, length as i64);
+        push!(self, dest as i64);
         self.f_smove(); // make a new string with the name from PAD
-        self.data[self.data[self.here_ptr] as usize] = pop!(self); // the string header
-        self.data[self.string_ptr] += length + 1; // update the free string pointer
+        let here = self.data[self.here_ptr] as usize;
+        self.data[here] = pop!(self) | self.u_current_wordlist(); // the string header
         self.data[self.last_ptr] = self.data[self.here_ptr];
         self.data[self.here_ptr] += 1;
     }
 
+    /// (does>) ( a -- ) makes a as the DOES> code of the word most recently made by CREATE
+    ///     does> compiles it, with the address of the code that follows.
+    ///
+    pub fn f_does_p(&mut self) {
+        if stack_ok!(self, 1, "(does>)") {
+            let code = pop!(self);
+            let cfa = self.data[self.last_ptr] as usize + 1;
+            if self.data[cfa] == DOES {
+                self.data[cfa + 1] = code;
+            } else {
+                let name = self.u_get_string(self.data[cfa - 1] as usize & ADDRESS_MASK);
+                self.u_error("does>", ERR_UNSUPPORTED, Some(name));
+            }
+        }
+    }
+
+    /// u_close_open_word writes the back pointer of a word made by CREATE, if it is still open
+    ///     The word is left alone if it has been forgotten since.
+    ///
+    pub fn u_close_open_word(&mut self) {
+        let nfa = self.open_word;
+        self.open_word = 0;
+        let here = self.data[self.here_ptr] as usize;
+        if nfa != 0 && self.data[self.last_ptr] as usize == nfa && here > nfa {
+            self.data[here] = nfa as i64 - 1; // write the back pointer
+            self.data[self.here_ptr] += 1;
+        }
+    }
+
     /*     /// variable <name> ( -- ) Creates a new variable in the dictionary
        ///     This is a good candidate for shifting to Forth
        ///     Variables use three words: a name pointer, the VARIABLE token, and the value
@@ -480,6 +674,9 @@ impl TF {
         let dest = pop!(self) as usize;
         let length = pop!(self) as usize;
         let source = pop!(self) as usize;
+        if !string_ok!(self, source, length + 1, "s-move") || !string_ok!(self, dest, length + 1, "s-move") {
+            return;
+        }
         // assuming both are counted, we begin with the count byte. Length should match the source count byte
         for i in 0..=length {
             self.strings[dest + i] = self.strings[source + i];
@@ -503,7 +700,7 @@ impl TF {
             if is_builtin != 0 {
                 println!(
                     "Builtin: {}",
-                    self.builtins[xt as usize & !BUILTIN_MASK].doc
+                    self.builtins[xt & !BUILTIN_MASK].doc
                 );
             } else {
                 // It's a definition of some kind
@@ -513,73 +710,123 @@ impl TF {
                         print!(": ");
                         let name = self.u_get_string(nfa);
                         print!("{name} ");
-                        let mut index = cfa as usize + 1; // skip the inner interpreter
-                        loop {
-                            let xt = self.data[index];
-                            match xt {
-                                LITERAL => {
-                                    print!("{} ", self.data[index as usize + 1]);
-                                    index += 1;
-                                }
-                                STRLIT => {
-                                    let s_addr = self.data[index as usize + 1] as usize;
-                                    print!("\" {}\" ", self.u_get_string(s_addr));
-                                    index += 1;
-                                }
-                                BRANCH => {
-                                    print!("branch:{} ", self.data[index as usize + 1]);
-                                    index += 1;
-                                }
-                                BRANCH0 => {
-                                    print!("branch0:{} ", self.data[index as usize + 1]);
-                                    index += 1;
-                                }
-                                ABORT => println!("abort "),
-                                BREAK => print!("exit "),
-                                EXIT => {
-                                    print!("; ");
-                                    if is_immed != 0 {
-                                        println!("immediate");
-                                    } else {
-                                        println!();
-                                    }
-                                    break;
-                                }
-                                _ => {
-                                    // it's a definition or a builtin
-                                    let mut cfa = self.data[index] as usize;
-                                    let mut mask = cfa & BUILTIN_MASK;
-                                    if mask == 0 {
-                                        let word = ADDRESS_MASK & self.data[self.data[index] as usize - 1] as usize; // nfa address
-                                        let name = self.u_get_string(word);
-                                        print!("{name} ");
-                                    } else {
-                                        mask = !BUILTIN_MASK;
-                                        cfa &= mask;
-                                        let name = &self.builtins[cfa].name;
-                                        print!("{name} ");
-                                    }
-                                }
-                            }
-                            index += 1;
-                        }
+                        self.u_see_code(cfa as usize + 1, is_immed); // skip the inner interpreter
                     }
                     CONSTANT => println!(
                         "Constant: {} = {}",
-                        self.u_get_string(self.data[cfa as usize - 1] as usize),
+                        self.u_get_string(nfa),
                         self.data[cfa as usize + 1]
                     ),
                     VARIABLE => println!(
                         "Variable: {} = {}",
-                        self.u_get_string(self.data[cfa as usize - 1] as usize),
+                        self.u_get_string(nfa),
                         self.data[cfa as usize + 1]
                     ),
+                    DOES => {
+                        print!("Created: {} ", self.u_get_string(nfa));
+                        let code = self.data[cfa as usize + 1] as usize;
+                        if code == 0 {
+                            println!();
+                        } else {
+                            print!("does> ");
+                            self.u_see_code(code, 0);
+                        }
+                    }
                     _ => self.msg.error("see", "Unrecognized type", None::<bool>),
                 }
             }
         }
     }
 
+    /// u_see_code decompiles compiled code starting at index, up to the EXIT that ends it
+    ///
+    fn u_see_code(&mut self, mut index: usize, is_immed: usize) {
+        loop {
+            let xt = self.data[index];
+            match xt {
+                LITERAL => {
+                    print!("{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                FLITERAL => {
+                    print!("{:e} ", f64::from_bits(self.data[index + 1] as u64));
+                    index += 1;
+                }
+                STRLIT => {
+                    let s_addr = self.data[index + 1] as usize;
+                    print!("\" {}\" ", self.u_get_string(s_addr));
+                    index += 1;
+                }
+                BRANCH => {
+                    print!("branch:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                BRANCH0 => {
+                    print!("branch0:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                DO => print!("do "),
+                QDO => {
+                    print!("?do:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                LOOP => {
+                    print!("loop:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                PLUS_LOOP => {
+                    print!("+loop:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                LEAVE => {
+                    print!("leave:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                LIT_PLUS => {
+                    print!("{} + ", self.data[index + 1]);
+                    index += 1;
+                }
+                LIT_MINUS => {
+                    print!("{} - ", self.data[index + 1]);
+                    index += 1;
+                }
+                DUP_BRANCH0 => {
+                    print!("dup branch0:{} ", self.data[index + 1]);
+                    index += 1;
+                }
+                OVER_OVER => print!("over over "),
+                R_FROM_DROP => print!("r> drop "),
+                ABORT => println!("abort "),
+                BREAK => print!("exit "),
+                EXIT => {
+                    print!("; ");
+                    if is_immed != 0 {
+                        println!("immediate");
+                    } else {
+                        println!();
+                    }
+                    break;
+                }
+                _ => {
+                    // it's a definition or a builtin
+                    let mut cfa = self.data[index] as usize;
+                    let mut mask = cfa & BUILTIN_MASK;
+                    if mask == 0 {
+                        let word = ADDRESS_MASK & self.data[(cfa & ADDRESS_MASK) - 1] as usize; // nfa address, without the tail call flag
+                        let name = self.u_get_string(word);
+                        print!("{name} ");
+                    } else {
+                        mask = !BUILTIN_MASK;
+                        cfa &= mask;
+                        let name = &self.builtins[cfa].name;
+                        print!("{name} ");
+                    }
+                }
+            }
+            index += 1;
+        }
+    }
+
     /*  fn f_d_pack(&mut self) {
         // pack the string in PAD and place it in the dictionary for a new word
         let data = self.f_string_at(addr);
@@ -593,15 +840,27 @@ impl TF {
 
     /// u_get_string returns a string from a Forth string address
     ///     Assumes the source string is counted (i.e. has its length in the first byte)
+    ///     An address outside string space aborts, and returns an empty string.
     ///
     pub fn u_get_string(&mut self, addr: usize) -> String {
-        let str_addr = (addr & ADDRESS_MASK) + 1; //
-        let last = str_addr + self.strings[addr] as usize;
-        let mut result = String::new();
-        for i in str_addr..last {
-            result.push(self.strings[i]);
+        match self.u_read_string(addr) {
+            Some(result) => result,
+            None => {
+                self.u_invalid_address("get-string", addr as i64);
+                String::new()
+            }
         }
-        result
+    }
+
+    /// u_read_string returns the counted string at addr, or None if it does not lie within string space
+    ///
+    pub fn u_read_string(&self, addr: usize) -> Option<String> {
+        let addr = addr & ADDRESS_MASK;
+        let count = *self.strings.get(addr)? as usize;
+        let str_addr = addr + 1;
+        self.strings
+            .get(str_addr..str_addr + count)
+            .map(|chars| chars.iter().collect())
     }
 
     /// u_set_string saves a counted string to a Forth string address
@@ -1,42 +1,11 @@
 /// Input-output words
-use crate::engine::{BUF_SIZE, FALSE, FILE_MODE_R_O, STACK_START, TF, TRUE};
+use crate::engine::{BUF_SIZE, FALSE, FILE_MODE_R_O, TF, TMP_START, TRUE};
 use crate::messages::Msg;
 use crate::files::{FileHandle, FType, FileMode};
 use std::cmp::min;
 use std::io::{self, Write, BufRead};
 use std::process::Command;
 
-macro_rules! stack_ok {
-    ($self:ident, $n: expr, $caller: expr) => {
-        if $self.stack_ptr <= STACK_START - $n {
-            true
-        } else {
-            $self.msg.error($caller, "Stack underflow", None::<bool>);
-            $self.f_abort();
-            false
-        }
-    };
-}
-macro_rules! pop {
-    ($self:ident) => {{
-        let r = $self.data[$self.stack_ptr];
-        //$self.data[$self.stack_ptr] = 999999;
-        $self.stack_ptr += 1;
-        r
-    }};
-}
-macro_rules! top {
-    ($self:ident) => {{
-        $self.data[$self.stack_ptr]
-    }};
-}
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
-}
-
     /// file I/O and system call
     /// 
     /// Most activity uses STDIN and STDOUT, but the system can also process source code
@@ -49,7 +18,6 @@ macro_rules! push {
     /// 
     /// Forth needs an i64 / usize as a file reference. This is achieved by creating a vector of file handles.
     /// Forth accesses files via an index into the vector.
-
 impl TF {
     /// (system) ( s -- ) Execute a shell command from the string on the stack (Unix-like operating systems)
     /// 
@@ -57,6 +25,9 @@ pub fn f_system_p(&mut self) {
     if stack_ok!(self, 1, "(system)") {
         let addr = pop!(self) as usize;
         let cmd_string = self.u_get_string(addr);
+        if self.get_abort_flag() {
+            return;
+        }
         let mut args = cmd_string.split_ascii_whitespace();
         //println!("args: {:?}", args);
         let mut cmd: Command;
@@ -69,28 +40,29 @@ pub fn f_system_p(&mut self) {
             println!("Adding {}", arg);
             cmd.arg(arg);
         }
-        let output = cmd.output().expect("(system) failed to execute command");
-        io::stdout().write_all(&output.stdout).unwrap();
-        io::stderr().write_all(&output.stderr).unwrap();
+        match cmd.output() {
+            Ok(output) => {
+                io::stdout().write_all(&output.stdout).ok();
+                io::stderr().write_all(&output.stderr).ok();
+            }
+            Err(e) => self.msg.error("(system)", "Failed to execute command", Some(e.to_string())),
+        }
    }
 }
 
     /// key ( -- c | 0 ) get a character and push on the stack, or zero if none available
     pub fn f_key(&mut self) {
         let reader = self.reader.last();
-        match reader {
-            Some(reader) => {
-                let c = reader.read_char();
-                match c {
-                    Some(c) => {
-                        push!(self, c as u8 as i64);
-                    }
-                    None => {
-                        push!(self, 0);
-                    }
+        if let Some(reader) = reader {
+            let c = reader.read_char();
+            match c {
+                Some(c) => {
+                    push!(self, c as u8 as i64);
+                }
+                None => {
+                    push!(self, 0);
                 }
             }
-            None => {}
         }
     }
 
@@ -105,6 +77,9 @@ pub fn f_system_p(&mut self) {
         if stack_ok!(self, 2, "accept") {
             let max_len = pop!(self);
             let dest = top!(self) as usize;
+            if !string_ok!(self, dest, max_len + 1, "accept") {
+                return;
+            }
             match self.reader.last_mut() {
                 Some(reader) => {
                     let l = reader.get_line();
@@ -120,10 +95,10 @@ pub fn f_system_p(&mut self) {
                             if self.reader.len() > 1 {
                                 // Reader 0 is stdin
                                 self.reader.pop(); // file goes out of scope and should be closed automatically
-                                push!(self, 0);
                             } else {
-                                panic!("Reader error - EOF in stdin");
+                                self.f_bye(); // EOF on stdin ends the session
                             }
+                            push!(self, 0);
                         }
                     }
                 }
@@ -142,6 +117,7 @@ pub fn f_system_p(&mut self) {
         self.f_accept();
         self.data[self.tib_size_ptr] = pop!(self); // update the TIB size pointer
         self.data[self.tib_in_ptr] = 1; // set the starting point in the TIB
+        self.data[self.tmp_ptr] = TMP_START as i64; // transient strings last until the end of the line
         pop!(self); // we don't need the address
     }
 
@@ -174,7 +150,7 @@ pub fn f_system_p(&mut self) {
     ///
     pub fn f_dot_s(&mut self) {
         print!("[ ");
-        for i in (self.stack_ptr..STACK_START).rev() {
+        for i in (self.stack_ptr..self.stack_start).rev() {
             print!("{} ", self.data[i]);
         }
         print!("] ");
@@ -189,8 +165,11 @@ pub fn f_system_p(&mut self) {
         if stack_ok!(self, 1, "include-file") {
             let addr = pop!(self) as usize;
             let file_name = self.u_get_string(addr);
+            if self.get_abort_flag() {
+                return;
+            }
             let mode = FILE_MODE_R_O;
-            let handle = self.u_open_file( &file_name, mode as i64);
+            let handle = self.u_open_file( &file_name, mode);
             match handle {
                 Some(handle) => {
                     self.reader.push(handle);
@@ -209,6 +188,9 @@ pub fn f_system_p(&mut self) {
             let mode = pop!(self);
             let addr = pop!(self) as usize;
             let name = self.u_get_string(addr);
+            if self.get_abort_flag() {
+                return;
+            }
             let handle = self.u_open_file(&name, mode);
             match handle {
                 Some(handle) => {
@@ -242,7 +224,6 @@ pub fn f_system_p(&mut self) {
                         return Some(fh);
                     }
                     None => {
-                        push!(self, FALSE);
                         self.msg.error(
                             "open-file",
                             "Failed to create new reader",
@@ -252,7 +233,6 @@ pub fn f_system_p(&mut self) {
                 }
             }
             Err(error) => {
-                push!(self, FALSE);
                 self.msg
                     .warning("open-file", error.to_string().as_str(), None::<bool>);
             }
@@ -317,11 +297,10 @@ pub fn f_system_p(&mut self) {
             if file_id < self.files.len() {
                 let string = self.u_get_string(addr)[0..chars - 1].to_owned();
                 // write the string to the file
-                match self.files[file_id].source {
-                    FType::File(ref mut f) => {
-                        f.write_all(&string.as_bytes()).expect("Error writing to file");
+                if let FType::File(ref mut f) = self.files[file_id].source {
+                    if f.write_all(string.as_bytes()).is_err() {
+                        self.msg.error("write-line", "Error writing to file", Some(file_id));
                     }
-                    _ => {}
                 }
             }
         }
@@ -1,36 +1,10 @@
 // Debugging help
 
-use crate::engine::{ADDRESS_MASK, BUILTIN_MASK, RET_START, STACK_START, TF,
-    VARIABLE, CONSTANT, LITERAL, STRLIT, DEFINITION, BRANCH, BRANCH0, ABORT, EXIT, BREAK};
+use crate::engine::{ADDRESS_MASK, BUILTIN_MASK, STACK_ROOM, TF,
+    VARIABLE, CONSTANT, LITERAL, FLITERAL, STRLIT, DEFINITION, BRANCH, BRANCH0, ABORT, EXIT, BREAK,
+    DO, QDO, LOOP, PLUS_LOOP, LEAVE, DOES, LIT_PLUS, LIT_MINUS, DUP_BRANCH0, OVER_OVER, R_FROM_DROP};
 use crate::messages::DebugLevel;
 
-macro_rules! stack_ok {
-    ($self:ident, $n: expr, $caller: expr) => {
-        if $self.stack_ptr <= STACK_START - $n {
-            true
-        } else {
-            $self.msg.error($caller, "Stack underflow", None::<bool>);
-            $self.f_abort();
-            false
-        }
-    };
-}
-macro_rules! pop {
-    ($self:ident) => {{
-        let r = $self.data[$self.stack_ptr];
-        //$self.data[$self.stack_ptr] = 999999;
-        $self.stack_ptr += 1;
-        r
-    }};
-}
-
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
-}
-
 impl TF {
     /// show-stack ( -- ) turns on stack printing at the time the prompt is issued
     ///
@@ -47,10 +21,25 @@ impl TF {
     /// DEPTH - print the number of items on the stack
     ///
     pub fn f_stack_depth(&mut self) {
-        let depth = STACK_START - self.stack_ptr;
+        let depth = self.stack_start - self.stack_ptr;
         push!(self, depth as i64);
     }
 
+    /// unused ( -- u ) pushes the number of cells that can still be allotted
+    ///     The dictionary keeps STACK_ROOM cells free below the stack, so they aren't counted.
+    ///
+    pub fn f_unused(&mut self) {
+        let here = self.data[self.here_ptr] as usize;
+        push!(self, self.stack_ptr.saturating_sub(here + STACK_ROOM + 1) as i64); // allot runs once u is popped
+    }
+
+    /// s-unused ( -- u ) pushes the number of characters free for permanent strings
+    ///
+    pub fn f_s_unused(&mut self) {
+        let s_here = self.data[self.string_ptr] as usize;
+        push!(self, self.string_size.saturating_sub(s_here) as i64);
+    }
+
     /// dbg ( n -- ) sets the current debug level used by the message module
     ///
     pub fn f_dbg(&mut self) {
@@ -83,12 +72,12 @@ impl TF {
         if mode == 0 { return };
 
         let mut contents = self.data[pc] as usize;
-        let is_builtin = if contents & BUILTIN_MASK != 0 { true } else { false };
+        let is_builtin = contents & BUILTIN_MASK != 0;
         contents &= ADDRESS_MASK;
         let mut c;
 
-        // Indent based on return stack depth
-        let depth = RET_START - self.return_ptr;
+        // Indent based on the depth of nested calls
+        let depth = self.calls.len() + 1;
         if depth > mode as usize { return; }
         print!("{depth}");
         for _i in 1..depth { print!(" "); }  
@@ -115,11 +104,23 @@ impl TF {
             't' => self.data[self.stepper_ptr] = 1,
             's' => {
                 match contents as i64 {
-                    VARIABLE | CONSTANT | DEFINITION => println!(" {} ", self.u_get_string(self.data[pc - 1] as usize)),
+                    VARIABLE | CONSTANT | DEFINITION => println!(" {} ", self.u_get_string(self.data[pc - 1] as usize & ADDRESS_MASK)),
                     LITERAL => println!(" {} ", self.data[pc + 1]),
+                    FLITERAL => println!(" {:e} ", f64::from_bits(self.data[pc + 1] as u64)),
                     STRLIT => println!(" {} ", self.u_get_string(self.data[pc + 1] as usize)),
                     BRANCH => println!(" BRANCH:{}", self.data[pc + 1]),
                     BRANCH0 => println!(" BRANCH0:{}", self.data[pc + 1]),
+                    DOES => println!(" DOES "),
+                    DO => println!(" DO "),
+                    QDO => println!(" ?DO:{}", self.data[pc + 1]),
+                    LOOP => println!(" LOOP:{}", self.data[pc + 1]),
+                    PLUS_LOOP => println!(" +LOOP:{}", self.data[pc + 1]),
+                    LEAVE => println!(" LEAVE:{}", self.data[pc + 1]),
+                    LIT_PLUS => println!(" {} + ", self.data[pc + 1]),
+                    LIT_MINUS => println!(" {} - ", self.data[pc + 1]),
+                    DUP_BRANCH0 => println!(" dup BRANCH0:{}", self.data[pc + 1]),
+                    OVER_OVER => println!(" over over "),
+                    R_FROM_DROP => println!(" r> drop "),
                     ABORT => println!(" ABORT "),
                     EXIT => println!(" EXIT "),
                     BREAK => println!(" BREAK "),
@@ -127,13 +128,13 @@ impl TF {
                         if is_builtin {
                             println!(" {} ", &self.builtins[contents].name);
                         } else {
-                            println!(" ->{}", self.u_get_string(self.data[contents - 1] as usize));
+                            println!(" ->{}", self.u_get_string(self.data[contents - 1] as usize & ADDRESS_MASK));
                         }
                     }
                 } 
             }
             'o' => self.data[self.stepper_ptr] = 0,
-            '\n' | _ => println!("Stepper: 's' for show, 't' for trace, 'o' for off."),
+            _ => println!("Stepper: 's' for show, 't' for trace, 'o' for off."),
         }
     }
 }
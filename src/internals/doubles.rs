@@ -0,0 +1,195 @@
+// Double-cell and mixed-precision arithmetic
+//
+// A double-cell number takes two cells on the stack, with the high (signed) cell on top of the low cell.
+// The words here convert to and from i128 and u128, so 128 bit intermediate results never overflow.
+// Division words that return a single-cell quotient throw -11 if it doesn't fit in a cell.
+
+use crate::engine::{FALSE, TF, TRUE};
+
+impl TF {
+    /// s>d ( n -- d ) converts a single-cell number to a double-cell number with the same value
+    ///
+    pub fn f_s_to_d(&mut self) {
+        if stack_ok!(self, 1, "s>d") {
+            let n = pop!(self);
+            self.u_push_double(n as i128);
+        }
+    }
+
+    /// m* ( n1 n2 -- d ) multiplies two signed numbers, giving a double-cell product
+    ///
+    pub fn f_m_star(&mut self) {
+        if stack_ok!(self, 2, "m*") {
+            let j = pop!(self);
+            let k = pop!(self);
+            self.u_push_double(k as i128 * j as i128);
+        }
+    }
+
+    /// um* ( u1 u2 -- ud ) multiplies two unsigned numbers, giving an unsigned double-cell product
+    ///
+    pub fn f_um_star(&mut self) {
+        if stack_ok!(self, 2, "um*") {
+            let j = pop!(self) as u64;
+            let k = pop!(self) as u64;
+            self.u_push_double((k as u128 * j as u128) as i128);
+        }
+    }
+
+    /// um/mod ( ud u1 -- u2 u3 ) divides an unsigned double by u1, giving the remainder u2 and quotient u3
+    ///
+    pub fn f_um_slash_mod(&mut self) {
+        if stack_ok!(self, 3, "um/mod") {
+            let divisor = pop!(self);
+            let ud = self.u_pop_double() as u128;
+            let u1 = divisor as u64 as u128;
+            if u1 == 0 {
+                self.u_division_error("um/mod", divisor);
+                return;
+            }
+            match u64::try_from(ud / u1) {
+                Ok(quot) => {
+                    push!(self, (ud % u1) as i64);
+                    push!(self, quot as i64);
+                }
+                Err(_) => self.u_division_error("um/mod", divisor),
+            }
+        }
+    }
+
+    /// sm/rem ( d n -- rem quot ) symmetric division: the quotient is rounded towards zero
+    ///
+    pub fn f_sm_slash_rem(&mut self) {
+        if stack_ok!(self, 3, "sm/rem") {
+            let n = pop!(self);
+            let d = self.u_pop_double();
+            if let Some((rem, quot)) = self.u_divide_double("sm/rem", d, n, false) {
+                push!(self, rem);
+                push!(self, quot);
+            }
+        }
+    }
+
+    /// fm/mod ( d n -- rem quot ) floored division: the quotient is rounded towards negative infinity,
+    ///     so the remainder has the sign of the divisor
+    ///
+    pub fn f_fm_slash_mod(&mut self) {
+        if stack_ok!(self, 3, "fm/mod") {
+            let n = pop!(self);
+            let d = self.u_pop_double();
+            if let Some((rem, quot)) = self.u_divide_double("fm/mod", d, n, true) {
+                push!(self, rem);
+                push!(self, quot);
+            }
+        }
+    }
+
+    /// */ ( n1 n2 n3 -- n4 ) multiplies n1 by n2 and divides by n3, with a double-cell intermediate product
+    ///
+    pub fn f_star_slash(&mut self) {
+        if stack_ok!(self, 3, "*/") {
+            let n3 = pop!(self);
+            let n2 = pop!(self);
+            let n1 = pop!(self);
+            if let Some((_, quot)) = self.u_divide_double("*/", n1 as i128 * n2 as i128, n3, false) {
+                push!(self, quot);
+            }
+        }
+    }
+
+    /// */mod ( n1 n2 n3 -- rem quot ) multiplies n1 by n2 and divides by n3, with a double-cell
+    ///     intermediate product
+    ///
+    pub fn f_star_slash_mod(&mut self) {
+        if stack_ok!(self, 3, "*/mod") {
+            let n3 = pop!(self);
+            let n2 = pop!(self);
+            let n1 = pop!(self);
+            if let Some((rem, quot)) = self.u_divide_double("*/mod", n1 as i128 * n2 as i128, n3, false) {
+                push!(self, rem);
+                push!(self, quot);
+            }
+        }
+    }
+
+    /// d+ ( d1 d2 -- d3 ) adds two double-cell numbers
+    ///
+    pub fn f_d_plus(&mut self) {
+        if stack_ok!(self, 4, "d+") {
+            let j = self.u_pop_double();
+            let k = self.u_pop_double();
+            self.u_push_double(k.wrapping_add(j));
+        }
+    }
+
+    /// d- ( d1 d2 -- d3 ) subtracts d2 from d1
+    ///
+    pub fn f_d_minus(&mut self) {
+        if stack_ok!(self, 4, "d-") {
+            let j = self.u_pop_double();
+            let k = self.u_pop_double();
+            self.u_push_double(k.wrapping_sub(j));
+        }
+    }
+
+    /// dnegate ( d1 -- d2 ) negates a double-cell number
+    ///
+    pub fn f_dnegate(&mut self) {
+        if stack_ok!(self, 2, "dnegate") {
+            let d = self.u_pop_double();
+            self.u_push_double(d.wrapping_neg());
+        }
+    }
+
+    /// d< ( d1 d2 -- flag ) returns true if d1 is less than d2
+    ///
+    pub fn f_d_less(&mut self) {
+        if stack_ok!(self, 4, "d<") {
+            let j = self.u_pop_double();
+            let k = self.u_pop_double();
+            push!(self, if k < j { TRUE } else { FALSE });
+        }
+    }
+
+    /// u_divide_double divides d by n, returning the remainder and quotient.
+    ///     The quotient is floored if floored is set, and otherwise rounded towards zero.
+    ///     Throws -10 if n is zero, or -11 if the quotient doesn't fit in a cell.
+    ///
+    fn u_divide_double(&mut self, caller: &str, d: i128, n: i64, floored: bool) -> Option<(i64, i64)> {
+        if n == 0 {
+            self.u_division_error(caller, n);
+            return None;
+        }
+        let (Some(mut quot), Some(mut rem)) = (d.checked_div(n as i128), d.checked_rem(n as i128)) else {
+            self.u_division_error(caller, n); // only the most negative double divided by -1
+            return None;
+        };
+        let n = n as i128;
+        if floored && rem != 0 && (rem < 0) != (n < 0) {
+            quot -= 1;
+            rem += n;
+        }
+        match i64::try_from(quot) {
+            Ok(quot) => Some((rem as i64, quot)),
+            Err(_) => {
+                self.u_division_error(caller, n as i64);
+                None
+            }
+        }
+    }
+
+    /// u_pop_double removes a double-cell number from the stack. Callers check the depth first.
+    ///
+    pub fn u_pop_double(&mut self) -> i128 {
+        let high = pop!(self);
+        let low = pop!(self);
+        ((high as i128) << 64) | low as u64 as i128
+    }
+
+    /// u_push_double places a double-cell number on the stack, low cell first
+    ///
+    pub fn u_push_double(&mut self, d: i128) {
+        push!(self, d as i64);
+        push!(self, (d >> 64) as i64);
+    }
+}
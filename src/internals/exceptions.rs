@@ -0,0 +1,184 @@
+// Exceptions: CATCH and THROW
+//
+// An exception is raised by storing its (non-zero) code in ABORT?. Every interpreter loop polls ABORT?,
+// so the Rust functions executing the current line unwind back to the nearest CATCH, or to the outer
+// interpreter if there is none. CATCH saves an exception frame describing the machine state before it
+// runs its xt, and restores that state if the xt throws.
+
+use crate::engine::{
+    ERR_ABORT, ERR_ABORT_Q, ERR_ALLOCATE, ERR_FREE, ERR_RESIZE, ERR_DICTIONARY_OVERFLOW, ERR_DIVIDE_BY_ZERO, ERR_FILE_IO, ERR_FLOAT_OVERFLOW, ERR_FLOAT_UNDERFLOW, ERR_HOLD_OVERFLOW, ERR_INVALID_ADDRESS, ERR_INVALID_ARGUMENT,
+    ERR_ORDER_OVERFLOW, ERR_ORDER_UNDERFLOW, ERR_OUT_OF_RANGE, ERR_RETURN_OVERFLOW, ERR_RETURN_UNDERFLOW,
+    ERR_STACK_OVERFLOW, ERR_STACK_UNDERFLOW, ERR_STRING_SPACE, ERR_UNDEFINED_WORD, ERR_UNSUPPORTED, FALSE, TF,
+};
+use std::fmt::Debug;
+
+/// exception_message returns the standard description of a THROW code
+///
+pub fn exception_message(code: i64) -> &'static str {
+    match code {
+        ERR_ABORT => "Aborted",
+        ERR_ABORT_Q => "Aborted with message",
+        ERR_STACK_OVERFLOW => "Stack overflow",
+        ERR_STACK_UNDERFLOW => "Stack underflow",
+        ERR_RETURN_OVERFLOW => "Return stack overflow",
+        ERR_RETURN_UNDERFLOW => "Return stack underflow",
+        ERR_DICTIONARY_OVERFLOW => "Dictionary overflow",
+        ERR_INVALID_ADDRESS => "Invalid address",
+        ERR_DIVIDE_BY_ZERO => "Division by zero",
+        ERR_OUT_OF_RANGE => "Result out of range",
+        ERR_UNDEFINED_WORD => "Undefined word",
+        ERR_HOLD_OVERFLOW => "Pictured numeric output string overflow",
+        ERR_UNSUPPORTED => "Unsupported operation",
+        ERR_INVALID_ARGUMENT => "Invalid numeric argument",
+        ERR_FILE_IO => "File I/O exception",
+        ERR_FLOAT_OVERFLOW => "Floating-point stack overflow",
+        ERR_FLOAT_UNDERFLOW => "Floating-point stack underflow",
+        ERR_ORDER_OVERFLOW => "Search-order overflow",
+        ERR_ORDER_UNDERFLOW => "Search-order underflow",
+        ERR_ALLOCATE => "Memory allocation failed",
+        ERR_FREE => "Memory release failed",
+        ERR_RESIZE => "Memory resize failed",
+        ERR_STRING_SPACE => "String space exhausted",
+        _ => "Uncaught exception",
+    }
+}
+
+/// The state saved by CATCH, and restored if its xt throws
+///
+struct CatchFrame {
+    stack: Vec<i64>,     // the data stack, below the xt
+    floats: Vec<f64>,    // the float stack
+    return_ptr: usize,   // the return stack is only ever popped back to this level, so the pointer is enough
+    tib: Option<String>, // the input source: contents of TIB and the parse position
+    tib_ptr: i64,
+    tib_size: i64,
+    tib_in: i64,
+    readers: usize,      // files opened by the xt are closed
+}
+
+impl CatchFrame {
+    fn save(forth: &TF) -> CatchFrame {
+        let tib_ptr = forth.data[forth.tib_ptr];
+        CatchFrame {
+            stack: forth.data[forth.stack_ptr..forth.stack_start].to_vec(),
+            floats: forth.floats.clone(),
+            return_ptr: forth.return_ptr,
+            tib: forth.u_read_string(tib_ptr as usize),
+            tib_ptr,
+            tib_size: forth.data[forth.tib_size_ptr],
+            tib_in: forth.data[forth.tib_in_ptr],
+            readers: forth.reader.len(),
+        }
+    }
+
+    fn restore(self, forth: &mut TF) {
+        forth.stack_ptr = forth.stack_start - self.stack.len();
+        forth.data[forth.stack_ptr..forth.stack_start].copy_from_slice(&self.stack);
+        forth.floats = self.floats;
+        forth.return_ptr = self.return_ptr;
+        forth.data[forth.tib_ptr] = self.tib_ptr;
+        if let Some(tib) = self.tib {
+            forth.u_save_string(&tib, self.tib_ptr as usize);
+        }
+        forth.data[forth.tib_size_ptr] = self.tib_size;
+        forth.data[forth.tib_in_ptr] = self.tib_in;
+        forth.reader.truncate(self.readers);
+    }
+}
+
+impl TF {
+    /// catch ( i*x xt -- j*x 0 | i*x n ) Execute xt, returning 0 if it completes normally.
+    ///     If xt (or anything it calls) throws n, the stacks and input source are restored
+    ///     to their state before xt was executed, and n is pushed.
+    ///
+    pub fn f_catch(&mut self) {
+        if stack_ok!(self, 1, "catch") {
+            let xt = pop!(self);
+            let frame = CatchFrame::save(self);
+            self.catch_depth += 1;
+            push!(self, xt);
+            self.f_execute();
+            self.catch_depth -= 1;
+            let code = self.get_var(self.abort_ptr);
+            if code == FALSE {
+                push!(self, 0);
+            } else {
+                frame.restore(self);
+                self.set_var(self.abort_ptr, FALSE);
+                push!(self, code);
+            }
+        }
+    }
+
+    /// throw ( k*x n -- k*x | i*x n ) If n is non-zero, raise exception n.
+    ///     Control passes to the most recent CATCH, or, if there is none, the error is reported
+    ///     and the outer interpreter resets the stacks and returns to the prompt.
+    ///
+    pub fn f_throw(&mut self) {
+        if stack_ok!(self, 1, "throw") {
+            let code = pop!(self);
+            if code != FALSE {
+                if code == ERR_ABORT {
+                    self.f_abort(); // ABORT is not reported as an error
+                } else {
+                    self.u_error("throw", code, Some(code));
+                }
+            }
+        }
+    }
+
+    /// (abort") ( s -- ) Print the counted string at s, unless a CATCH will handle it, and throw -2
+    ///     Compiled by ABORT"
+    ///
+    pub fn f_abort_quote_p(&mut self) {
+        if stack_ok!(self, 1, "(abort\")") {
+            let addr = pop!(self) as usize;
+            let message = self.u_get_string(addr);
+            if self.catch_depth == 0 {
+                println!("{message}");
+            }
+            self.u_throw(ERR_ABORT_Q);
+        }
+    }
+
+    /// u_throw raises an exception, without reporting it
+    ///     The first exception raised wins: anything thrown while unwinding is ignored.
+    ///
+    pub fn u_throw(&mut self, code: i64) {
+        if !self.get_abort_flag() {
+            self.set_var(self.abort_ptr, code);
+        }
+    }
+
+    /// u_error raises an exception, printing an error message unless a CATCH is active to handle it
+    ///
+    pub fn u_error<T: Debug>(&mut self, caller: &str, code: i64, val: Option<T>) {
+        if self.catch_depth == 0 && !self.get_abort_flag() {
+            self.msg.error(caller, exception_message(code), val);
+        }
+        self.u_throw(code);
+    }
+
+    /// u_clear_exception is called by the outer interpreter when an exception was not caught
+    ///     It empties the stacks, leaves compile mode, undoing an unfinished definition, and closes any files
+    ///     being included.
+    ///     Returns the exception code, or FALSE if there was no exception.
+    ///
+    pub fn u_clear_exception(&mut self, readers: usize) -> i64 {
+        let code = self.get_var(self.abort_ptr);
+        if code != FALSE {
+            self.f_clear();
+            self.floats.clear();
+            self.return_ptr = self.ret_start;
+            self.calls.clear();
+            if self.get_compile_mode() {
+                self.u_undo_header();
+            }
+            self.header_mark = None;
+            self.set_compile_mode(false);
+            self.reader.truncate(readers);
+            self.set_var(self.abort_ptr, FALSE);
+        }
+        code
+    }
+}
@@ -0,0 +1,198 @@
+// Floating-point words
+//
+// Floats live on their own stack, kept on the Rust side as a Vec<f64> rather than in data space.
+// In memory a float takes one cell, holding the bits of the f64, so F@ and F! work on ordinary cell addresses.
+// Float literals such as 1.5e0 are recognized by the text interpreter, and compiled with the FLITERAL opcode.
+
+use crate::engine::{
+    ERR_FLOAT_OVERFLOW, ERR_FLOAT_UNDERFLOW, ERR_OUT_OF_RANGE, FALSE, FLITERAL, MAX_FLOAT_DEPTH, TF, TRUE,
+};
+
+macro_rules! fpop2_fpush1 {
+    // Helper macro
+    ($self:ident, $word:expr, $expression:expr) => {
+        if $self.u_fstack_ok(2, $word) {
+            let j = $self.u_fpop();
+            let k = $self.u_fpop();
+            $self.u_fpush($expression(k, j), $word);
+        }
+    };
+}
+macro_rules! fpop1_fpush1 {
+    // Helper macro
+    ($self:ident, $word:expr, $expression:expr) => {
+        if $self.u_fstack_ok(1, $word) {
+            let k = $self.u_fpop();
+            $self.u_fpush($expression(k), $word);
+        }
+    };
+}
+
+impl TF {
+    pub fn f_f_plus(&mut self) {
+        fpop2_fpush1!(self, "f+", |a, b| a + b);
+    }
+
+    pub fn f_f_minus(&mut self) {
+        fpop2_fpush1!(self, "f-", |a, b| a - b);
+    }
+
+    pub fn f_f_times(&mut self) {
+        fpop2_fpush1!(self, "f*", |a, b| a * b);
+    }
+
+    /// f/ ( F: r1 r2 -- r3 ) divides r1 by r2. Division by zero gives an infinity or NaN, as IEEE arithmetic does.
+    ///
+    pub fn f_f_divide(&mut self) {
+        fpop2_fpush1!(self, "f/", |a, b| a / b);
+    }
+
+    pub fn f_fsqrt(&mut self) {
+        fpop1_fpush1!(self, "fsqrt", f64::sqrt);
+    }
+
+    pub fn f_fsin(&mut self) {
+        fpop1_fpush1!(self, "fsin", f64::sin);
+    }
+
+    pub fn f_fcos(&mut self) {
+        fpop1_fpush1!(self, "fcos", f64::cos);
+    }
+
+    pub fn f_fexp(&mut self) {
+        fpop1_fpush1!(self, "fexp", f64::exp);
+    }
+
+    pub fn f_fln(&mut self) {
+        fpop1_fpush1!(self, "fln", f64::ln);
+    }
+
+    pub fn f_fdup(&mut self) {
+        if self.u_fstack_ok(1, "fdup") {
+            let r = self.floats[self.floats.len() - 1];
+            self.u_fpush(r, "fdup");
+        }
+    }
+
+    pub fn f_fswap(&mut self) {
+        if self.u_fstack_ok(2, "fswap") {
+            let n = self.floats.len();
+            self.floats.swap(n - 1, n - 2);
+        }
+    }
+
+    pub fn f_fdrop(&mut self) {
+        if self.u_fstack_ok(1, "fdrop") {
+            self.u_fpop();
+        }
+    }
+
+    /// f< ( -- flag ) ( F: r1 r2 -- ) true if r1 is less than r2
+    ///
+    pub fn f_f_less(&mut self) {
+        if self.u_fstack_ok(2, "f<") {
+            let j = self.u_fpop();
+            let k = self.u_fpop();
+            push!(self, if k < j { TRUE } else { FALSE });
+        }
+    }
+
+    /// f0= ( -- flag ) ( F: r -- ) true if r is zero
+    ///
+    pub fn f_f_zero_equal(&mut self) {
+        if self.u_fstack_ok(1, "f0=") {
+            let k = self.u_fpop();
+            push!(self, if k == 0.0 { TRUE } else { FALSE });
+        }
+    }
+
+    /// s>f ( n -- ) ( F: -- r ) converts an integer to a float
+    ///
+    pub fn f_s_to_f(&mut self) {
+        if stack_ok!(self, 1, "s>f") {
+            let n = pop!(self);
+            self.u_fpush(n as f64, "s>f");
+        }
+    }
+
+    /// f>s ( -- n ) ( F: r -- ) converts a float to an integer, truncating towards zero
+    ///     Throws -11 if r is not a number, or is too large for a cell.
+    ///
+    pub fn f_f_to_s(&mut self) {
+        if self.u_fstack_ok(1, "f>s") {
+            let r = self.u_fpop().trunc();
+            if r.is_nan() || r < i64::MIN as f64 || r >= i64::MAX as f64 {
+                self.u_error("f>s", ERR_OUT_OF_RANGE, Some(r));
+            } else {
+                push!(self, r as i64);
+            }
+        }
+    }
+
+    /// f@ ( a -- ) ( F: -- r ) fetches the float stored in the cell at address a
+    ///
+    pub fn f_f_fetch(&mut self) {
+        if stack_ok!(self, 1, "f@") {
+            let addr = pop!(self);
+            if data_ok!(self, addr, "f@") {
+                self.u_fpush(f64::from_bits(self.data[addr as usize] as u64), "f@");
+            }
+        }
+    }
+
+    /// f! ( a -- ) ( F: r -- ) stores a float in the cell at address a
+    ///
+    pub fn f_f_store(&mut self) {
+        if stack_ok!(self, 1, "f!") && self.u_fstack_ok(1, "f!") {
+            let addr = pop!(self);
+            let r = self.u_fpop();
+            if data_ok!(self, addr, "f!") {
+                self.data[addr as usize] = r.to_bits() as i64;
+            }
+        }
+    }
+
+    /// f. ( F: r -- ) prints the top of the float stack, followed by a space
+    ///
+    pub fn f_f_dot(&mut self) {
+        if self.u_fstack_ok(1, "f.") {
+            print!("{} ", self.u_fpop());
+        }
+    }
+
+    /// u_fliteral compiles a float literal: the FLITERAL opcode, followed by the bits of r
+    ///
+    pub fn u_fliteral(&mut self, r: f64) {
+        push!(self, FLITERAL);
+        self.f_comma();
+        push!(self, r.to_bits() as i64);
+        self.f_comma();
+    }
+
+    /// u_fstack_ok checks that there are at least n values on the float stack, throwing -45 if not
+    ///
+    pub fn u_fstack_ok(&mut self, n: usize, caller: &str) -> bool {
+        if self.floats.len() >= n {
+            true
+        } else {
+            self.u_error(caller, ERR_FLOAT_UNDERFLOW, None::<bool>);
+            false
+        }
+    }
+
+    /// u_fpush places a value on the float stack, throwing -44 if it is full
+    ///
+    pub fn u_fpush(&mut self, r: f64, caller: &str) {
+        if self.floats.len() < MAX_FLOAT_DEPTH {
+            self.floats.push(r);
+        } else {
+            self.u_error(caller, ERR_FLOAT_OVERFLOW, None::<bool>);
+        }
+    }
+
+    /// u_fpop removes the top of the float stack. Callers check the depth first; an empty stack yields 0.
+    ///
+    fn u_fpop(&mut self) -> f64 {
+        self.floats.pop().unwrap_or_default()
+    }
+}
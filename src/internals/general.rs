@@ -1,40 +1,12 @@
 // General-purpose builtin words
 
-use crate::engine::{FALSE, STACK_START, TF, TRUE};
+use crate::engine::{
+    ERR_DIVIDE_BY_ZERO, ERR_OUT_OF_RANGE, ERR_RETURN_OVERFLOW, ERR_RETURN_UNDERFLOW, FALSE, OVERFLOW_CHECKED, OVERFLOW_SATURATING, TF,
+    TRUE,
+};
 use std::time::{Instant, Duration};
 use std::thread;
 
-macro_rules! stack_ok {
-    ($self:ident, $n: expr, $caller: expr) => {
-        if $self.stack_ptr <= STACK_START - $n {
-            true
-        } else {
-            $self.msg.error($caller, "Stack underflow", None::<bool>);
-            $self.f_abort();
-            false
-        }
-    };
-}
-macro_rules! pop {
-    ($self:ident) => {{
-        let r = $self.data[$self.stack_ptr];
-        //$self.data[$self.stack_ptr] = 999999;
-        $self.stack_ptr += 1;
-        r
-    }};
-}
-macro_rules! top {
-    ($self:ident) => {{
-        $self.data[$self.stack_ptr]
-    }};
-}
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
-}
-
 macro_rules! pop2_push1 {
     // Helper macro
     ($self:ident, $word:expr, $expression:expr) => {
@@ -65,27 +37,97 @@ impl TF {
     /// Basic Forth operations on the stack.
     ///
     pub fn f_plus(&mut self) {
-        if stack_ok!(self, 2, "+") {
-            let a = pop!(self);
-            let b = pop!(self);
-            push!(self, a + b);
-        };
+        self.u_arithmetic("+", i64::checked_add, i64::wrapping_add, i64::saturating_add);
     }
 
     pub fn f_minus(&mut self) {
-        pop2_push1!(self, "-", |a, b| a - b);
+        self.u_arithmetic("-", i64::checked_sub, i64::wrapping_sub, i64::saturating_sub);
     }
 
     pub fn f_times(&mut self) {
-        pop2_push1!(self, "*", |a, b| a * b);
+        self.u_arithmetic("*", i64::checked_mul, i64::wrapping_mul, i64::saturating_mul);
     }
 
+    /// u_arithmetic applies one of three versions of an operator to the top two values on the stack,
+    ///     chosen by OVERFLOW-MODE. In checked mode, an overflow throws -11.
+    ///
+    fn u_arithmetic(
+        &mut self,
+        caller: &str,
+        checked: fn(i64, i64) -> Option<i64>,
+        wrapping: fn(i64, i64) -> i64,
+        saturating: fn(i64, i64) -> i64,
+    ) {
+        if stack_ok!(self, 2, caller) {
+            let j = pop!(self);
+            let k = pop!(self);
+            match self.data[self.overflow_ptr] {
+                OVERFLOW_CHECKED => match checked(k, j) {
+                    Some(result) => {
+                        push!(self, result);
+                    }
+                    None => self.u_error(caller, ERR_OUT_OF_RANGE, Some((k, j))),
+                },
+                OVERFLOW_SATURATING => {
+                    push!(self, saturating(k, j));
+                }
+                _ => {
+                    push!(self, wrapping(k, j));
+                }
+            }
+        }
+    }
+
+    /// / ( n1 n2 -- n3 ) symmetric division: the quotient is rounded towards zero, as are mod and /mod.
+    ///     fm/mod gives floored division.
+    ///
     pub fn f_divide(&mut self) {
-        pop2_push1!(self, "/", |a, b| a / b);
+        if stack_ok!(self, 2, "/") {
+            let j = pop!(self);
+            let k = pop!(self);
+            match k.checked_div(j) {
+                Some(q) => {
+                    push!(self, q);
+                }
+                None => self.u_division_error("/", j),
+            }
+        }
     }
 
     pub fn f_mod(&mut self) {
-        pop2_push1!(self, "mod", |a, b| a % b);
+        if stack_ok!(self, 2, "mod") {
+            let j = pop!(self);
+            let k = pop!(self);
+            match k.checked_rem(j) {
+                Some(r) => {
+                    push!(self, r);
+                }
+                None => self.u_division_error("mod", j),
+            }
+        }
+    }
+
+    /// /mod ( n1 n2 -- rem quot ) symmetric division, with the remainder taking the sign of n1
+    ///
+    pub fn f_slash_mod(&mut self) {
+        if stack_ok!(self, 2, "/mod") {
+            let j = pop!(self);
+            let k = pop!(self);
+            match (k.checked_rem(j), k.checked_div(j)) {
+                (Some(r), Some(q)) => {
+                    push!(self, r);
+                    push!(self, q);
+                }
+                _ => self.u_division_error("/mod", j),
+            }
+        }
+    }
+
+    /// u_division_error throws -10 for a zero divisor, or -11 if the quotient overflows (MIN / -1)
+    ///
+    pub(crate) fn u_division_error(&mut self, caller: &str, divisor: i64) {
+        let code = if divisor == 0 { ERR_DIVIDE_BY_ZERO } else { ERR_OUT_OF_RANGE };
+        self.u_error(caller, code, None::<bool>);
     }
 
     pub fn f_less(&mut self) {
@@ -113,7 +155,7 @@ impl TF {
     }
 
     pub fn f_clear(&mut self) {
-        self.stack_ptr = STACK_START;
+        self.stack_ptr = self.stack_start;
     }
 
     pub fn f_bye(&mut self) {
@@ -175,11 +217,78 @@ impl TF {
         }
     }
 
+    pub fn f_xor(&mut self) {
+        pop2_push1!(self, "xor", |a, b| a ^ b);
+    }
+
+    pub fn f_invert(&mut self) {
+        pop1_push1!(self, "invert", |a: i64| !a);
+    }
+
+    /// lshift ( x u -- x ) shifts x left by u bits, filling with zeros. Shifting by 64 or more gives 0.
+    ///
+    pub fn f_lshift(&mut self) {
+        pop2_push1!(self, "lshift", |a: i64, b: i64| if (b as u64) < 64 { ((a as u64) << b) as i64 } else { 0 });
+    }
+
+    /// rshift ( x u -- x ) shifts x right by u bits, filling with zeros. Shifting by 64 or more gives 0.
+    ///
+    pub fn f_rshift(&mut self) {
+        pop2_push1!(self, "rshift", |a: i64, b: i64| if (b as u64) < 64 { ((a as u64) >> b) as i64 } else { 0 });
+    }
+
+    /// arshift ( n u -- n ) shifts n right by u bits, copying the sign bit
+    ///
+    pub fn f_arshift(&mut self) {
+        pop2_push1!(self, "arshift", |a: i64, b: i64| a >> (b as u64).min(63));
+    }
+
+    pub fn f_2star(&mut self) {
+        pop1_push1!(self, "2*", |a: i64| a.wrapping_shl(1));
+    }
+
+    /// 2/ ( n -- n ) an arithmetic shift right by one bit, which rounds towards negative infinity
+    ///
+    pub fn f_2slash(&mut self) {
+        pop1_push1!(self, "2/", |a: i64| a >> 1);
+    }
+
+    pub fn f_u_less(&mut self) {
+        pop2_push1!(self, "u<", |a: i64, b: i64| if (a as u64) < (b as u64) { TRUE } else { FALSE });
+    }
+
+    pub fn f_u_greater(&mut self) {
+        pop2_push1!(self, "u>", |a: i64, b: i64| if (a as u64) > (b as u64) { TRUE } else { FALSE });
+    }
+
+    pub fn f_umin(&mut self) {
+        pop2_push1!(self, "umin", |a: i64, b: i64| (a as u64).min(b as u64) as i64);
+    }
+
+    pub fn f_umax(&mut self) {
+        pop2_push1!(self, "umax", |a: i64, b: i64| (a as u64).max(b as u64) as i64);
+    }
+
+    /// within ( n lo hi -- flag ) true if lo <= n < hi. The comparison wraps around, so it works for
+    ///     signed and unsigned ranges alike, and if hi is below lo, flags everything outside hi..lo.
+    ///
+    pub fn f_within(&mut self) {
+        if stack_ok!(self, 3, "within") {
+            let hi = pop!(self);
+            let lo = pop!(self);
+            let n = pop!(self);
+            let inside = (n.wrapping_sub(lo) as u64) < (hi.wrapping_sub(lo) as u64);
+            push!(self, if inside { TRUE } else { FALSE });
+        }
+    }
+
     /// @ (get) ( a -- n ) loads the value at address a onto the stack
     pub fn f_get(&mut self) {
         if stack_ok!(self, 1, "@") {
             let addr = pop!(self);
-            push!(self, self.data[addr as usize]);
+            if data_ok!(self, addr, "@") {
+                push!(self, self.data[addr as usize]);
+            }
         }
     }
 
@@ -189,14 +298,20 @@ impl TF {
         if stack_ok!(self, 2, "!") {
             let addr = pop!(self);
             let value = pop!(self);
-            self.data[addr as usize] = value;
+            if data_ok!(self, addr, "!") {
+                self.data[addr as usize] = value;
+            }
         }
     }
 
     /// >r ( n -- ) Pops the stack, placing the value on the return stack
     ///
+    ///     Throws -5 if the return stack has grown down to the data stack.
+    ///
     pub fn f_to_r(&mut self) {
-        if stack_ok!(self, 1, ">r") {
+        if self.return_ptr <= self.ret_end {
+            self.u_error(">r", ERR_RETURN_OVERFLOW, None::<bool>);
+        } else if stack_ok!(self, 1, ">r") {
             let value = pop!(self);
             self.return_ptr -= 1;
             self.data[self.return_ptr] = value;
@@ -206,70 +321,106 @@ impl TF {
     /// r> ( -- n ) Pops the return stack, pushing the value to the calculation stack
     ///
     pub fn f_r_from(&mut self) {
-        push!(self, self.data[self.return_ptr]);
-        self.return_ptr += 1;
+        if self.return_ptr < self.ret_start {
+            push!(self, self.data[self.return_ptr]);
+            self.return_ptr += 1;
+        } else {
+            self.u_return_stack_underflow("r>");
+        }
     }
 
     /// r@ ( -- n ) Gets the top value from the return stack, pushing the value to the calculation stack
     ///
     pub fn f_r_get(&mut self) {
-        push!(self, self.data[self.return_ptr]);
+        if self.return_ptr < self.ret_start {
+            push!(self, self.data[self.return_ptr]);
+        } else {
+            self.u_return_stack_underflow("r@");
+        }
     }
 
     /// i ( -- n ) Pushes the current loop index to the calculation stack
+    ///     Loop frames are two cells deep, with the index on top: [index][limit]
     ///
     pub fn f_i(&mut self) {
-        push!(self, self.data[self.return_ptr]);
+        self.u_loop_index(0, "i");
     }
 
     /// j ( -- n ) Pushes the second level (outer) loop index to the calculation stack
     ///
     pub fn f_j(&mut self) {
-        push!(self, self.data[self.return_ptr + 1]);
+        self.u_loop_index(1, "j");
     }
 
-    /// c@ - ( s -- c ) read a character from a string and place on the stack
+    /// k ( -- n ) Pushes the third level loop index to the calculation stack
     ///
-    pub fn f_c_get(&mut self) {
-        if stack_ok!(self, 1, "c@") {
-            let s_address = pop!(self) as usize;
-            push!(self, self.strings[s_address] as u8 as i64);
+    pub fn f_k(&mut self) {
+        self.u_loop_index(2, "k");
+    }
+
+    /// unloop ( -- ) Discards the parameters of the current loop, so the definition can exit from inside it
+    ///
+    pub fn f_unloop(&mut self) {
+        if self.return_ptr + 1 < self.ret_start {
+            self.return_ptr += 2;
+        } else {
+            self.u_return_stack_underflow("unloop");
         }
     }
 
-    /// c! - ( c s -- ) read a character from a string and place on the stack
-    pub fn f_c_store(&mut self) {
-        if stack_ok!(self, 2, "c!") {
-            let s_address = pop!(self) as usize;
-            self.strings[s_address] = pop!(self) as u8 as char;
+    /// u_loop_index pushes the index of the loop nested level frames out from the innermost
+    ///
+    fn u_loop_index(&mut self, level: usize, caller: &str) {
+        let addr = self.return_ptr + 2 * level;
+        if addr < self.ret_start {
+            push!(self, self.data[addr]);
+        } else {
+            self.u_return_stack_underflow(caller);
         }
     }
 
+    /// u_return_stack_underflow reports an attempt to read past the bottom of the return stack
+    ///
+    fn u_return_stack_underflow(&mut self, caller: &str) {
+        self.u_error(caller, ERR_RETURN_UNDERFLOW, None::<bool>);
+    }
+
     /// s-copy (s-from s-to -- s-to )
     pub fn f_s_copy(&mut self) {
         if stack_ok!(self, 2, "s-copy") {
             let dest = pop!(self) as usize;
             let result_ptr = dest as i64;
             let source = pop!(self) as usize;
+            if !string_ok!(self, source, 1, "s-copy") {
+                return;
+            }
             let length = self.strings[source] as u8 as usize + 1;
+            if !string_ok!(self, source, length, "s-copy") || !string_ok!(self, dest, length, "s-copy") {
+                return;
+            }
             let mut i = 0;
             while i < length {
                 self.strings[dest + i] = self.strings[source + i];
                 i += 1;
             }
-            self.data[self.string_ptr] += length as i64;
             push!(self, result_ptr);
         }
     }
+
     /// s-create ( s-from -- s-to ) copies a counted string into the next empty space, updating the free space pointer
+    ///     Throws -256 if string space is exhausted.
+    ///
     pub fn f_s_create(&mut self) {
         if stack_ok!(self, 1, "s-create") {
             let source = top!(self) as usize;
-            let length = self.strings[source] as usize;
-            let dest = self.data[self.string_ptr];
-            push!(self, dest); // destination
-            self.f_s_copy();
-            self.data[self.string_ptr] += length as i64 + 1;
+            if !string_ok!(self, source, 1, "s-create") {
+                return;
+            }
+            let length = self.strings[source] as u8 as usize;
+            if let Some(dest) = self.u_string_alloc(length, "s-create") {
+                push!(self, dest as i64);
+                self.f_s_copy();
+            }
         }
     }
 
@@ -0,0 +1,237 @@
+// Dynamic memory: ALLOCATE, FREE and RESIZE
+//
+// The heap is a fixed region of data space between the stack and the return stack, so allocated blocks are
+// ordinary cell addresses that @ ! MOVE and the rest can use. The Rust side keeps the bookkeeping: a map of
+// the blocks in use, and a map of the free blocks, which are merged with their neighbours when a block is
+// freed so that they never touch. Blocks are found first fit, and their contents are cleared.
+//
+// Following Forth-2012, failures are reported by returning a non-zero ior rather than by throwing. The ior
+// is the THROW code for the word that failed, so a program can pass it straight to THROW.
+
+use crate::engine::{ERR_ALLOCATE, ERR_FREE, ERR_RESIZE, TF};
+use std::collections::BTreeMap;
+
+/// Heap keeps track of the blocks allocated in the heap region of data space
+///
+pub struct Heap {
+    start: usize,
+    end: usize,
+    used: BTreeMap<usize, usize>, // address -> size of each allocated block
+    free: BTreeMap<usize, usize>, // address -> size of each free block
+}
+
+/// HeapStats summarises the state of the heap, for heap-stats
+///
+pub struct HeapStats {
+    pub blocks: usize,
+    pub used: usize,
+    pub free: usize,
+    pub fragments: usize,
+    pub largest: usize,
+}
+
+impl Heap {
+    /// new creates an empty heap covering the cells from start up to end
+    ///
+    pub fn new(start: usize, end: usize) -> Heap {
+        let mut heap = Heap { start, end, used: BTreeMap::new(), free: BTreeMap::new() };
+        heap.clear();
+        heap
+    }
+
+    /// clear frees every block, for when the memory they were in has been reused
+    ///
+    pub fn clear(&mut self) {
+        self.used.clear();
+        self.free.clear();
+        if self.end > self.start {
+            self.free.insert(self.start, self.end - self.start);
+        }
+    }
+
+    /// allocate finds a free block of size cells, returning its address
+    ///
+    pub fn allocate(&mut self, size: usize) -> Option<usize> {
+        let size = size.max(1); // every block has an address of its own
+        let (&addr, &space) = self.free.iter().find(|(_, &space)| space >= size)?;
+        self.free.remove(&addr);
+        if space > size {
+            self.free.insert(addr + size, space - size);
+        }
+        self.used.insert(addr, size);
+        Some(addr)
+    }
+
+    /// release returns a block to the free list, merging it with any free neighbours
+    ///     Returns false if addr isn't the address of an allocated block.
+    ///
+    pub fn release(&mut self, addr: usize) -> bool {
+        let Some(size) = self.used.remove(&addr) else {
+            return false;
+        };
+        let (mut start, mut total) = (addr, size);
+        if let Some((&before, &space)) = self.free.range(..addr).next_back() {
+            if before + space == addr {
+                self.free.remove(&before);
+                start = before;
+                total += space;
+            }
+        }
+        if let Some(space) = self.free.remove(&(addr + size)) {
+            total += space;
+        }
+        self.free.insert(start, total);
+        true
+    }
+
+    /// grow extends the block at addr in place to size cells, if it can be done
+    ///     A block can always shrink. It can grow if the free block that follows it is large enough.
+    ///
+    pub fn grow(&mut self, addr: usize, size: usize) -> bool {
+        let size = size.max(1);
+        let Some(&old) = self.used.get(&addr) else {
+            return false;
+        };
+        if size <= old {
+            self.used.insert(addr, size);
+            self.u_add_free(addr + size, old - size);
+            return true;
+        }
+        match self.free.get(&(addr + old)).copied() {
+            Some(space) if old + space >= size => {
+                self.free.remove(&(addr + old));
+                if old + space > size {
+                    self.free.insert(addr + size, old + space - size);
+                }
+                self.used.insert(addr, size);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// u_add_free adds cells given back by a shrinking block, merging them with a free block that follows
+    ///
+    fn u_add_free(&mut self, addr: usize, size: usize) {
+        if size == 0 {
+            return;
+        }
+        let following = self.free.remove(&(addr + size)).unwrap_or(0);
+        self.free.insert(addr, size + following);
+    }
+
+    /// size returns the size of the allocated block at addr
+    ///
+    pub fn size(&self, addr: usize) -> Option<usize> {
+        self.used.get(&addr).copied()
+    }
+
+    /// blocks lists the address and size of each allocated block, lowest first
+    ///
+    pub fn blocks(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.used.iter().map(|(&addr, &size)| (addr, size))
+    }
+
+    /// stats counts the blocks in use and the free space
+    ///
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            blocks: self.used.len(),
+            used: self.used.values().sum(),
+            free: self.free.values().sum(),
+            fragments: self.free.len(),
+            largest: self.free.values().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+impl TF {
+    /// allocate ( u -- a-addr ior ) Allocates u cells from the heap
+    ///     ior is 0 on success. If there isn't a free block large enough, a-addr is 0 and ior is -59.
+    ///
+    pub fn f_allocate(&mut self) {
+        if stack_ok!(self, 1, "allocate") {
+            let size = pop!(self);
+            match usize::try_from(size).ok().and_then(|size| self.heap.allocate(size)) {
+                Some(addr) => {
+                    let size = self.heap.size(addr).unwrap_or(0);
+                    self.data[addr..addr + size].fill(0);
+                    push!(self, addr as i64);
+                    push!(self, 0);
+                }
+                None => {
+                    push!(self, 0);
+                    push!(self, ERR_ALLOCATE);
+                }
+            }
+        }
+    }
+
+    /// free ( a-addr -- ior ) Returns a block to the heap
+    ///     ior is -60 if a-addr isn't the address of an allocated block, such as one already freed.
+    ///
+    pub fn f_free(&mut self) {
+        if stack_ok!(self, 1, "free") {
+            let addr = pop!(self);
+            let freed = usize::try_from(addr).is_ok_and(|addr| self.heap.release(addr));
+            push!(self, if freed { 0 } else { ERR_FREE });
+        }
+    }
+
+    /// resize ( a-addr1 u -- a-addr2 ior ) Changes the size of a block to u cells, moving it if necessary
+    ///     The contents are kept, up to the smaller of the two sizes. On failure, a-addr1 is left
+    ///     unchanged, and ior is -61.
+    ///
+    pub fn f_resize(&mut self) {
+        if stack_ok!(self, 2, "resize") {
+            let size = pop!(self);
+            let addr = pop!(self);
+            let block = usize::try_from(addr).ok().and_then(|a| Some((a, self.heap.size(a)?)));
+            let (Some((old_addr, old_size)), Ok(size)) = (block, usize::try_from(size)) else {
+                push!(self, addr);
+                push!(self, ERR_RESIZE);
+                return;
+            };
+            if self.heap.grow(old_addr, size) {
+                if size > old_size {
+                    self.data[old_addr + old_size..old_addr + size].fill(0);
+                }
+                push!(self, addr);
+                push!(self, 0);
+                return;
+            }
+            match self.heap.allocate(size) {
+                Some(new_addr) => {
+                    self.data.copy_within(old_addr..old_addr + old_size, new_addr);
+                    self.data[new_addr + old_size..new_addr + size].fill(0);
+                    self.heap.release(old_addr);
+                    push!(self, new_addr as i64);
+                    push!(self, 0);
+                }
+                None => {
+                    push!(self, addr);
+                    push!(self, ERR_RESIZE);
+                }
+            }
+        }
+    }
+
+    /// heap-stats ( -- ) Lists the blocks in use, then the free space and how fragmented it is
+    ///     Fragmentation is the share of the free space that is outside the largest free block.
+    ///
+    pub fn f_heap_stats(&mut self) {
+        println!("   address    cells");
+        for (addr, size) in self.heap.blocks() {
+            println!("{addr:>10} {size:>8}");
+        }
+        let stats = self.heap.stats();
+        let fragmentation = match stats.free {
+            0 => 0,
+            free => 100 * (free - stats.largest) / free,
+        };
+        println!(
+            "used: {} blocks / {} cells, free: {} blocks / {} cells, largest free: {}, fragmentation: {}%",
+            stats.blocks, stats.used, stats.fragments, stats.free, stats.largest, fragmentation
+        );
+    }
+}
@@ -0,0 +1,441 @@
+// Dictionary images
+//
+// An image is a snapshot of the Forth side of the engine: DATA, STRINGS, the *_ptr fields that locate
+// the system variables, and the names of the builtins. Loading an image replaces the dictionary built by
+// cold_start and the core library, so a session can be resumed without re-parsing any source.
+//
+// Builtins are referenced in the dictionary by their index in the builtins table, which depends on the
+// order of add_builtins and on any closures registered by the host. The image records the name for each
+// index, and loading walks the dictionary, re-binding every builtin reference to the current table.
+// ' and ['] give a builtin's flagged index as its xt, so xts held as literals, or in variables, constants
+// and words made by CREATE, are re-bound too.
+//
+// Only the dictionary and the strings below s-here are used when an image is loaded, so an image can be
+// loaded into an engine with different memory sizes, as long as they are large enough to hold it.
+//
+// File layout (all values little endian):
+//     magic, version, data size, string size
+//     number of pointers, then each *_ptr field
+//     number of builtins, then each name as a length and UTF-8 bytes
+//     DATA as i64 cells, then STRINGS as u32 characters
+//
+// A turnkey executable is a copy of the f2 binary with an image appended, followed by the name of the
+// word to run at startup and a trailer giving their lengths:
+//     executable, image, entry word, image length, entry word length, turnkey magic
+
+use crate::api::ForthError;
+use crate::internals::memory::DataSpace;
+use crate::engine::{
+    ADDRESS_MASK, BRANCH, BRANCH0, BUILTIN_MASK, CONSTANT, DEFINITION, DOES, ERR_FILE_IO, ERR_UNDEFINED_WORD, FLITERAL, LEAVE, LITERAL, LOOP,
+    MAX_DATA_SIZE, MAX_STRING_SIZE, PLUS_LOOP, QDO, STRLIT, TF, VARIABLE, LIT_PLUS, LIT_MINUS, DUP_BRANCH0,
+};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+const IMAGE_MAGIC: &[u8; 8] = b"f2image\0";
+const IMAGE_VERSION: u32 = 8;
+const IMAGE_POINTERS: usize = 20;
+const TURNKEY_MAGIC: &[u8; 8] = b"f2turnky";
+const TURNKEY_TRAILER: u64 = 24; // two lengths and the magic
+
+/// ImageReader steps through the bytes of an image, failing cleanly if the file is truncated
+///
+struct ImageReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ImageReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ForthError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(ForthError::BadImage("file is truncated".to_owned()));
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, ForthError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, ForthError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, ForthError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, ForthError> {
+        Ok(self.u64()? as usize)
+    }
+}
+
+impl TF {
+    /// (save-image) ( s -- ) Save the dictionary to the file named by the string at s
+    ///     save-image <file> is the usual way to call it.
+    ///
+    pub fn f_save_image_p(&mut self) {
+        if stack_ok!(self, 1, "(save-image)") {
+            let addr = pop!(self) as usize;
+            let path = self.u_get_string(addr);
+            if self.get_abort_flag() {
+                return;
+            }
+            if let Err(e) = self.save_image(&path) {
+                self.u_error("save-image", ERR_FILE_IO, Some(e.to_string()));
+            }
+        }
+    }
+
+    /// (turnkey) ( s1 s2 -- ) Write a turnkey executable to the file named by s1, which runs word s2
+    ///     turnkey <file> <word> is the usual way to call it.
+    ///
+    pub fn f_turnkey_p(&mut self) {
+        if stack_ok!(self, 2, "(turnkey)") {
+            let entry_addr = pop!(self) as usize;
+            let path_addr = pop!(self) as usize;
+            let entry = self.u_get_string(entry_addr);
+            let path = self.u_get_string(path_addr);
+            if self.get_abort_flag() {
+                return;
+            }
+            match self.save_turnkey(&path, &entry) {
+                Ok(()) => {}
+                Err(ForthError::UndefinedWord(name)) => {
+                    self.u_error("turnkey", ERR_UNDEFINED_WORD, Some(name));
+                }
+                Err(e) => self.u_error("turnkey", ERR_FILE_IO, Some(e.to_string())),
+            }
+        }
+    }
+
+    /// save_image writes the dictionary, string space and builtin names to a versioned image file
+    ///     The stacks and any open files are not saved.
+    ///
+    pub fn save_image(&mut self, path: &str) -> Result<(), ForthError> {
+        fs::write(path, self.image_bytes())
+            .map_err(|e| ForthError::WriteFailed(format!("{path}: {e}")))
+    }
+
+    /// save_turnkey writes a copy of the running executable with the image and an entry word appended
+    ///     When the copy is run, it loads the image and executes the entry word instead of the interpreter.
+    ///
+    pub fn save_turnkey(&mut self, path: &str, entry: &str) -> Result<(), ForthError> {
+        if self.find_word(entry).is_none() {
+            return Err(ForthError::UndefinedWord(entry.to_owned()));
+        }
+        let write_failed = |e: std::io::Error| ForthError::WriteFailed(format!("{path}: {e}"));
+        let exe = std::env::current_exe().map_err(write_failed)?;
+        let mut bytes = fs::read(&exe).map_err(write_failed)?;
+        if let Some((old_image, old_entry)) = embedded_image(&exe) {
+            // we are a turnkey executable ourselves: start from the plain engine
+            let appended = old_image.len() + old_entry.len() + TURNKEY_TRAILER as usize;
+            bytes.truncate(bytes.len() - appended);
+        }
+        let image = self.image_bytes();
+        bytes.extend_from_slice(&image);
+        bytes.extend_from_slice(entry.as_bytes());
+        bytes.extend_from_slice(&(image.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&(entry.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(TURNKEY_MAGIC);
+        fs::write(path, bytes).map_err(write_failed)?;
+        let permissions = fs::metadata(&exe).map_err(write_failed)?.permissions();
+        fs::set_permissions(path, permissions).map_err(write_failed)
+    }
+
+    /// image_bytes returns the contents of an image file for the current dictionary
+    ///     A word made by CREATE is finished first, so that its data is part of the dictionary.
+    ///
+    pub fn image_bytes(&mut self) -> Vec<u8> {
+        self.u_close_open_word();
+        let mut bytes = Vec::with_capacity(self.data_size * 8 + self.string_size * 4 + 4096);
+        bytes.extend_from_slice(IMAGE_MAGIC);
+        bytes.extend_from_slice(&IMAGE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.data_size as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.string_size as u64).to_le_bytes());
+        let pointers = self.u_image_pointers();
+        bytes.extend_from_slice(&(pointers.len() as u32).to_le_bytes());
+        for p in pointers {
+            bytes.extend_from_slice(&(p as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.builtins.len() as u64).to_le_bytes());
+        for builtin in &self.builtins {
+            bytes.extend_from_slice(&(builtin.name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(builtin.name.as_bytes());
+        }
+        for cell in self.data.cells() {
+            bytes.extend_from_slice(&cell.to_le_bytes());
+        }
+        for c in self.strings.iter() {
+            bytes.extend_from_slice(&(*c as u32).to_le_bytes());
+        }
+        bytes
+    }
+
+    /// load_image replaces the dictionary with one saved by save_image
+    ///     cold_start must have been called first, and any closures the image uses must be registered,
+    ///     so that builtins can be re-bound by name. The stacks are cleared.
+    ///     The memory sizes are not changed, so the dictionary and strings in the image must fit.
+    ///     Nothing is changed unless the whole image is valid.
+    ///
+    pub fn load_image(&mut self, path: &str) -> Result<(), ForthError> {
+        let bytes = fs::read(path).map_err(|_| ForthError::FileNotFound(path.to_owned()))?;
+        self.load_image_bytes(&bytes)
+    }
+
+    /// load_image_bytes loads an image from memory, as returned by image_bytes
+    ///
+    pub fn load_image_bytes(&mut self, bytes: &[u8]) -> Result<(), ForthError> {
+        let mut image = ImageReader { bytes, pos: 0 };
+        if image.take(IMAGE_MAGIC.len())? != IMAGE_MAGIC {
+            return Err(ForthError::BadImage("not an f2 image".to_owned()));
+        }
+        let version = image.u32()?;
+        if version != IMAGE_VERSION {
+            return Err(ForthError::BadImage(format!(
+                "image version {version} is not supported (expected {IMAGE_VERSION})"
+            )));
+        }
+        let (data_size, string_size) = (image.usize()?, image.usize()?);
+        if data_size > MAX_DATA_SIZE || string_size > MAX_STRING_SIZE {
+            return Err(ForthError::BadImage(format!(
+                "image memory size {data_size}/{string_size} is too large"
+            )));
+        }
+        if image.u32()? as usize != IMAGE_POINTERS {
+            return Err(ForthError::BadImage("unexpected number of pointers".to_owned()));
+        }
+        let mut pointers = [0; IMAGE_POINTERS];
+        for p in pointers.iter_mut() {
+            *p = image.usize()?;
+            if DataSpace::offset(*p) >= data_size {
+                return Err(ForthError::BadImage(format!("pointer {p} is out of range")));
+            }
+        }
+
+        // map each builtin index in the image to the index of the same name in the current table
+        let current: HashMap<&str, usize> = self
+            .builtins
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.name.as_str(), i))
+            .collect();
+        let count = image.usize()?;
+        let mut rebind = Vec::new();
+        for _ in 0..count {
+            let len = image.u32()? as usize;
+            let name = String::from_utf8_lossy(image.take(len)?).into_owned();
+            match current.get(name.as_str()) {
+                Some(&index) => rebind.push(index),
+                None => {
+                    return Err(ForthError::BadImage(format!("builtin {name} is not available")));
+                }
+            }
+        }
+
+        let mut data = DataSpace::new(data_size);
+        for cell in data.cells_mut() {
+            *cell = image.i64()?;
+        }
+        let mut strings = vec![' '; string_size];
+        for c in strings.iter_mut() {
+            *c = char::from_u32(image.u32()?)
+                .ok_or_else(|| ForthError::BadImage("invalid character in string space".to_owned()))?;
+        }
+        let here = u_rebind_builtins(&mut data, pointers[0], &rebind)?;
+        if pointers.iter().any(|&p| p >= here) {
+            return Err(ForthError::BadImage("pointer is outside the dictionary".to_owned()));
+        }
+        let s_here = data[pointers[6]] as usize; // pointers[6] is string_ptr
+        if s_here > string_size {
+            return Err(ForthError::BadImage("string space is corrupt".to_owned()));
+        }
+        let used = DataSpace::offset(here);
+        if here >= self.stack_start || s_here > self.string_size {
+            return Err(ForthError::BadImage(format!(
+                "the image needs {used} cells of data space and {s_here} characters of string space"
+            )));
+        }
+
+        self.data.cells_mut().fill(0);
+        self.data.cells_mut()[..used].copy_from_slice(&data.cells()[..used]);
+        self.strings.fill(' ');
+        self.strings[..s_here].copy_from_slice(&strings[..s_here]);
+        self.u_set_image_pointers(&pointers);
+        self.stack_ptr = self.stack_start;
+        self.return_ptr = self.ret_start;
+        self.calls.clear();
+        self.heap.clear();
+        self.open_word = 0;
+        self.index.borrow_mut().clear();
+        self.set_compile_mode(false);
+        self.set_abort_flag(false);
+        Ok(())
+    }
+
+    /// u_image_pointers lists the *_ptr fields saved in an image. here_ptr must come first.
+    ///
+    fn u_image_pointers(&self) -> [usize; IMAGE_POINTERS] {
+        [
+            self.here_ptr,
+            self.context_ptr,
+            self.eval_ptr,
+            self.base_ptr,
+            self.pad_ptr,
+            self.tmp_ptr,
+            self.string_ptr,
+            self.last_ptr,
+            self.hld_ptr,
+            self.state_ptr,
+            self.pc_ptr,
+            self.abort_ptr,
+            self.tib_ptr,
+            self.tib_size_ptr,
+            self.tib_in_ptr,
+            self.stepper_ptr,
+            self.current_ptr,
+            self.order_ptr,
+            self.wordlists_ptr,
+            self.overflow_ptr,
+        ]
+    }
+
+    /// u_set_image_pointers restores the *_ptr fields, in the order of u_image_pointers
+    ///
+    fn u_set_image_pointers(&mut self, p: &[usize; IMAGE_POINTERS]) {
+        self.here_ptr = p[0];
+        self.context_ptr = p[1];
+        self.eval_ptr = p[2];
+        self.base_ptr = p[3];
+        self.pad_ptr = p[4];
+        self.tmp_ptr = p[5];
+        self.string_ptr = p[6];
+        self.last_ptr = p[7];
+        self.hld_ptr = p[8];
+        self.state_ptr = p[9];
+        self.pc_ptr = p[10];
+        self.abort_ptr = p[11];
+        self.tib_ptr = p[12];
+        self.tib_size_ptr = p[13];
+        self.tib_in_ptr = p[14];
+        self.stepper_ptr = p[15];
+        self.current_ptr = p[16];
+        self.order_ptr = p[17];
+        self.wordlists_ptr = p[18];
+        self.overflow_ptr = p[19];
+    }
+}
+
+/// image_sizes returns the data and string sizes of the engine that saved an image
+///
+pub fn image_sizes(bytes: &[u8]) -> Result<(usize, usize), ForthError> {
+    let mut image = ImageReader { bytes, pos: 0 };
+    if image.take(IMAGE_MAGIC.len())? != IMAGE_MAGIC || image.u32()? != IMAGE_VERSION {
+        return Err(ForthError::BadImage("not a supported f2 image".to_owned()));
+    }
+    Ok((image.usize()?, image.usize()?))
+}
+
+/// embedded_image returns the image and entry word appended to a turnkey executable, if there are any
+///     Only the trailer is read from an ordinary executable, so the check is cheap.
+///
+pub fn embedded_image(exe: &Path) -> Option<(Vec<u8>, String)> {
+    let mut file = File::open(exe).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len < TURNKEY_TRAILER {
+        return None;
+    }
+    let mut trailer = [0; TURNKEY_TRAILER as usize];
+    file.seek(SeekFrom::Start(len - TURNKEY_TRAILER)).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+    if &trailer[16..] != TURNKEY_MAGIC {
+        return None;
+    }
+    let image_len = u64::from_le_bytes(trailer[0..8].try_into().unwrap());
+    let entry_len = u64::from_le_bytes(trailer[8..16].try_into().unwrap());
+    let start = (len - TURNKEY_TRAILER)
+        .checked_sub(image_len)?
+        .checked_sub(entry_len)?;
+    let mut image = vec![0; image_len as usize];
+    let mut entry = vec![0; entry_len as usize];
+    file.seek(SeekFrom::Start(start)).ok()?;
+    file.read_exact(&mut image).ok()?;
+    file.read_exact(&mut entry).ok()?;
+    Some((image, String::from_utf8(entry).ok()?))
+}
+
+/// u_rebind_builtins rewrites the builtin references in an image's dictionary to use current indexes
+///     The dictionary is walked entry by entry using the back pointers, so only code fields, the
+///     compiled words in definitions and builtin xts held as data are changed. Strings and branch offsets
+///     are skipped. An xt is a cell holding exactly a flagged builtin index, which no ordinary number
+///     below 2^61 can be mistaken for.
+///     Returns the value of HERE, the size of the dictionary.
+///
+fn u_rebind_builtins(data: &mut DataSpace, here_ptr: usize, rebind: &[usize]) -> Result<usize, ForthError> {
+    let corrupt = || ForthError::BadImage("the dictionary is corrupt".to_owned());
+    let here = data[here_ptr] as usize;
+    if DataSpace::offset(here) == 0 || DataSpace::offset(here) > data.cells().len() / 2 {
+        return Err(corrupt());
+    }
+    let mut end = here - 1; // the back pointer following the most recent word
+    let mut link = data[end] as usize;
+    while link > 0 {
+        if link >= end || data.get(link).is_none() {
+            return Err(corrupt()); // back pointers must always lead down through the dictionary
+        }
+        let cfa = link + 2; // link is followed by the name field, then the code field
+        match data.get(cfa).copied() {
+            Some(DEFINITION) => {
+                let mut i = cfa + 1;
+                while i < end {
+                    let code = data[i];
+                    match code {
+                        LITERAL => {
+                            u_rebind_xt(&mut data[i + 1], rebind); // perhaps an xt from [']
+                            i += 1;
+                        }
+                        FLITERAL | STRLIT | BRANCH | BRANCH0 | QDO | LOOP | PLUS_LOOP | LEAVE | LIT_PLUS | LIT_MINUS
+                        | DUP_BRANCH0 => i += 1, // skip the argument
+                        _ if code as usize & BUILTIN_MASK != 0 => {
+                            let old = code as usize & !BUILTIN_MASK;
+                            let new = rebind.get(old).ok_or_else(corrupt)?;
+                            data[i] = (new | BUILTIN_MASK) as i64;
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            Some(VARIABLE | CONSTANT | DOES) => {
+                let first = if data[cfa] == DOES { cfa + 2 } else { cfa + 1 }; // after the DOES> code
+                for cell in data[first.min(end)..end].iter_mut() {
+                    u_rebind_xt(cell, rebind);
+                }
+            }
+            Some(code) if code as usize & BUILTIN_MASK != 0 => {
+                let old = code as usize & !BUILTIN_MASK;
+                let new = rebind.get(old).ok_or_else(corrupt)?;
+                data[cfa] = (new | BUILTIN_MASK) as i64;
+            }
+            _ => {}
+        }
+        end = link;
+        link = data[link] as usize;
+    }
+    Ok(here)
+}
+
+/// u_rebind_xt re-binds a cell of data if it holds the xt of a builtin, leaving any other value alone
+///
+fn u_rebind_xt(cell: &mut i64, rebind: &[usize]) {
+    let value = *cell as usize;
+    if value & !ADDRESS_MASK == BUILTIN_MASK {
+        if let Some(new) = rebind.get(value & ADDRESS_MASK) {
+            *cell = (new | BUILTIN_MASK) as i64;
+        }
+    }
+}
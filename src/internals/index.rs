@@ -0,0 +1,96 @@
+// Hashed dictionary lookup
+//
+// The dictionary stays a linked list in data space, which Forth code is free to change: FORGET moves CONTEXT
+// back, and definitions move it forward. So the index is a cache, checked against CONTEXT on every lookup.
+// Words are indexed in the order they were added, newest last, so bringing the index up to date means
+// popping any words that have been forgotten, then walking back from CONTEXT to the newest word still indexed.
+// A corrupt back pointer only hides the words between it and the newest word indexed before it.
+
+use crate::engine::{ADDRESS_MASK, WORDLIST_MASK, WORDLIST_SHIFT};
+use crate::internals::memory::DataSpace;
+use std::collections::HashMap;
+
+/// WordIndex maps each name to the wordlist and cfa of every word defined with it, newest last
+///
+#[derive(Default)]
+pub struct WordIndex {
+    words: HashMap<String, Vec<(i64, usize)>>,
+    added: Vec<(usize, i64, String)>, // nfa, name field and name of each indexed word, in dictionary order
+}
+
+impl WordIndex {
+    /// find returns the cfa of the newest word with this name, in the first wordlist of order that has one
+    ///
+    pub fn find(&self, name: &str, order: &[i64]) -> Option<usize> {
+        let words = self.words.get(name)?;
+        order.iter().find_map(|&wid| {
+            words.iter().rev().find(|(w, _)| *w == wid).map(|(_, cfa)| *cfa)
+        })
+    }
+
+    /// is_cfa is true if cfa is the code field of an indexed word
+    ///
+    pub fn is_cfa(&self, cfa: usize) -> bool {
+        self.added.binary_search_by_key(&cfa.wrapping_sub(1), |(nfa, ..)| *nfa).is_ok()
+    }
+
+    /// clear empties the index, for when the whole dictionary is replaced
+    ///
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.added.clear();
+    }
+
+    /// sync brings the index up to date with a dictionary whose newest visible word has its name field at head
+    ///
+    pub fn sync(&mut self, data: &DataSpace, strings: &[char], head: usize) {
+        // forget any words above the head, or a word at the head that has been replaced since
+        while let Some(&(nfa, name_field, _)) = self.added.last() {
+            if nfa < head || (nfa == head && data.get(nfa) == Some(&name_field)) {
+                break;
+            }
+            self.u_pop();
+        }
+        let newest = self.added.last().map_or(0, |(nfa, ..)| *nfa);
+        if newest == head {
+            return;
+        }
+        // walk back from the head to the newest word already indexed
+        let mut new_words = Vec::new();
+        let mut link = head.wrapping_sub(1);
+        while link > 0 && link + 1 > newest {
+            let (Some(&next), Some(_)) = (data.get(link), data.get(link + 1)) else {
+                break; // a corrupt back pointer, outside data space: index what has been found
+            };
+            new_words.push(link + 1);
+            if next as usize >= link {
+                break; // a corrupt back pointer: index what has been found
+            }
+            link = next as usize;
+        }
+        // if the walk stopped short of the newest indexed word, a back pointer is bad. The words already
+        // indexed are kept, since throwing them away would lose every word below the bad link.
+        for nfa in new_words.into_iter().rev() {
+            let name_field = data[nfa];
+            let addr = name_field as usize & ADDRESS_MASK;
+            let count = strings.get(addr).map_or(0, |c| *c as usize);
+            let name: String = strings.get(addr + 1..addr + 1 + count).unwrap_or_default().iter().collect();
+            let wid = ((name_field as usize & WORDLIST_MASK) >> WORDLIST_SHIFT) as i64;
+            self.words.entry(name.clone()).or_default().push((wid, nfa + 1));
+            self.added.push((nfa, name_field, name));
+        }
+    }
+
+    /// u_pop removes the most recently indexed word
+    ///
+    fn u_pop(&mut self) {
+        if let Some((_, _, name)) = self.added.pop() {
+            if let Some(words) = self.words.get_mut(&name) {
+                words.pop();
+                if words.is_empty() {
+                    self.words.remove(&name);
+                }
+            }
+        }
+    }
+}
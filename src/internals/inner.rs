@@ -4,23 +4,12 @@
 ///
 use crate::engine::{
     ABORT, ADDRESS_MASK, BRANCH, BRANCH0, BUILTIN, BUILTIN_MASK, CONSTANT, DEFINITION, EXIT,
-    LITERAL, BREAK, RET_START, STRLIT, TF, VARIABLE,
+    LITERAL, BREAK, ERR_RETURN_OVERFLOW, ERR_RETURN_UNDERFLOW, ERR_STACK_OVERFLOW, ERR_UNSUPPORTED, STRLIT, TF, VARIABLE, FLITERAL,
+    DO, QDO, LOOP, PLUS_LOOP, LEAVE, DOES, MAX_CALL_DEPTH, TAIL_CALL_MASK, LIT_PLUS, LIT_MINUS, DUP_BRANCH0, OVER_OVER, R_FROM_DROP,
 };
-
-macro_rules! pop {
-    ($self:ident) => {{
-        let r = $self.data[$self.stack_ptr];
-        //$self.data[$self.stack_ptr] = 999999;
-        $self.stack_ptr += 1;
-        r
-    }};
-}
-macro_rules! push {
-    ($self:ident, $val:expr) => {
-        $self.stack_ptr -= 1;
-        $self.data[$self.stack_ptr] = $val;
-    };
-}
+use crate::internals::builtin::BuiltinCode;
+use crate::internals::memory::DataSpace;
+use std::rc::Rc;
 
 impl TF {
     /// Executes the builtin at the next address in DATA
@@ -29,9 +18,30 @@ impl TF {
     ///
     pub fn i_builtin(&mut self) {
         let code = pop!(self);
-        let op = &self.builtins[code as usize];
-        let func = op.code;
-        func(self);
+        if code < 0 || code as usize >= self.builtins.len() {
+            self.u_invalid_address("i_builtin", code);
+            return;
+        }
+        let caller = std::mem::replace(&mut self.current_builtin, code as usize);
+        match &self.builtins[code as usize].code {
+            BuiltinCode::Function(func) => {
+                let func = *func;
+                func(self);
+            }
+            BuiltinCode::Closure(closure) => {
+                // a closure can't be re-entered while it is running, because it holds mutable state
+                let closure = Rc::clone(closure);
+                let running = closure.try_borrow_mut();
+                match running {
+                    Ok(mut func) => func(self),
+                    Err(_) => {
+                        let name = self.builtins[code as usize].name.clone();
+                        self.u_error("i_builtin", ERR_UNSUPPORTED, Some(name));
+                    }
+                }
+            }
+        }
+        self.current_builtin = caller;
     }
 
     /// Places the address of the adjacent variable on the stack
@@ -43,6 +53,20 @@ impl TF {
         push!(self, val); // address of the value
     }
 
+    /// Places the address of the data of a word made by CREATE on the stack, then runs its DOES> code
+    ///
+    ///    [ index of i_does ] [ address of DOES> code, or 0 ] [ data ... ] in a created word
+    ///
+    pub fn i_does(&mut self) {
+        let addr = pop!(self);
+        push!(self, addr + 1); // address of the data
+        let code = self.data[addr as usize];
+        if code != 0 {
+            push!(self, code);
+            self.i_definition();
+        }
+    }
+
     /// Places the value of the adjacent constant on the stack
     ///
     ///    [ index of i_constant ] [ constant value ] in a compiled word
@@ -72,57 +96,74 @@ impl TF {
     ///    Each entry is one or two cells, and may be an inner interpreter code (opcode), with or without an argument,
     ///    or a defined word. For space efficiency, builtin words and user defined (colon) words are
     ///    represented by the cfa of their definition, overlaid with a flag. The interpreter calls the builtin code.
-    ///    For nested definitions, the inner interpreter pushes the program counter (PC) and continues.
+    ///    For nested definitions, the inner interpreter pushes the program counter (PC) onto CALLS and continues.
     ///    When the end of a definition is found, the PC is restored from the previous caller.
     ///
     ///    Most data is represented by an address, so self.data[pc] is the cfa of the word referenced.
     ///    Each operation advances the pc to the next token.
     ///
+    ///    Only builtins and a few opcodes can raise an exception or turn the stepper on, so the abort flag
+    ///    and STEPPER are checked after those, and on returns and backward jumps, rather than for every cell.
+    ///
     ///    cfa means the code field address (the address in data space of the opcode to be executed)
     ///    nfa means the name field address (a pointer to the string naming the word)
     ///    xt  means the execution token - a value that tells the engine what to do
     ///
     pub fn i_definition(&mut self) {
         let mut pc = pop!(self) as usize; // This is the start of the definition: first word after the inner interpreter opcode
-        push!(self, 0); // this is how we know when we're done
-        self.f_to_r();
+        let base = self.calls.len(); // this is how we know when we're done
+        let mut stepping = self.data[self.stepper_ptr] != 0;
         loop {
             // each time round the loop should be one word
-            if pc == 0 || self.get_abort_flag() {
-                self.return_ptr = RET_START; // clear the return stack
-                return; // we've completed the last exit or encountered an error
+            if DataSpace::offset(pc) + 1 >= self.data_size {
+                self.u_invalid_address("i_definition", pc as i64); // a corrupt definition or return address
+                break;
+            }
+            if stepping {
+                self.u_step(pc);
+                stepping = self.data[self.stepper_ptr] != 0;
             }
             let code = self.data[pc];
-            self.u_step(pc);
             match code {
                 BUILTIN => {
                     self.msg
                         .error("i_definition", "Found BUILTIN???", Some(code));
-                    self.f_r_from();
-                    pc = pop!(self) as usize;
+                    match self.u_return(base) {
+                        Some(addr) => pc = addr,
+                        None => break,
+                    }
                 }
                 VARIABLE => {
                     // this means we've pushed into a variable and are seeing the inner interpreter
-                    pc += 1;
-                    push!(self, pc as i64); // the address of the variable's data
-                    self.f_r_from();
-                    pc = pop!(self) as usize;
+                    if !self.u_push_from(pc as i64 + 1, pc) {
+                        break; // no room for the address of the variable's data
+                    }
+                    match self.u_return(base) {
+                        Some(addr) => pc = addr,
+                        None => break,
+                    }
                 }
                 CONSTANT => {
-                    pc += 1;
-                    push!(self, self.data[pc]); // the value of the constant
-                    self.f_r_from();
-                    pc = pop!(self) as usize;
+                    if !self.u_push_from(self.data[pc + 1], pc) {
+                        break; // no room for the value of the constant
+                    }
+                    match self.u_return(base) {
+                        Some(addr) => pc = addr,
+                        None => break,
+                    }
                 }
-                LITERAL => {
-                    pc += 1;
-                    push!(self, self.data[pc]); // the data stored in the current definition
-                    pc += 1;
+                LITERAL | STRLIT => {
+                    if !self.u_push_from(self.data[pc + 1], pc) {
+                        break; // no room for the number, or string address, stored in the definition
+                    }
+                    pc += 2;
                 }
-                STRLIT => {
-                    pc += 1;
-                    push!(self, self.data[pc] as i64); // the string address of the data
-                    pc += 1;
+                FLITERAL => {
+                    self.u_fpush(f64::from_bits(self.data[pc + 1] as u64), "fliteral");
+                    if self.get_abort_flag() {
+                        break;
+                    }
+                    pc += 2;
                 }
                 DEFINITION => {
                     pc += 1;
@@ -131,59 +172,250 @@ impl TF {
                 }
                 BRANCH => {
                     // Unconditional jump based on self.data[pc + 1]
-                    pc += 1;
-                    let offset = self.data[pc];
-                    if offset < 0 {
-                        pc -= offset.abs() as usize;
-                    } else {
-                        pc += offset as usize;
+                    let offset = self.data[pc + 1];
+                    pc = (pc as i64 + 1).wrapping_add(offset) as usize; // a bad offset is caught by the address check
+                    if offset <= 0 && self.get_abort_flag() {
+                        break;
                     }
-                    // pc += 1; // skip over the offset
                 }
                 BRANCH0 => {
-                    pc += 1;
                     if pop!(self) == 0 {
-                        let offset = self.data[pc];
-                        if offset < 0 {
-                            pc -= offset.abs() as usize;
-                        } else {
-                            pc += offset as usize;
+                        let offset = self.data[pc + 1];
+                        pc = (pc as i64 + 1).wrapping_add(offset) as usize;
+                        if self.get_abort_flag() {
+                            break; // the flag wasn't there to pop
+                        }
+                    } else {
+                        pc += 2; // skip over the offset
+                    }
+                }
+                DO => {
+                    if !stack_ok!(self, 2, "do") {
+                        break;
+                    }
+                    let index = pop!(self);
+                    let limit = pop!(self);
+                    if !self.u_push_loop(limit, index, "do") {
+                        break;
+                    }
+                    pc += 1;
+                }
+                QDO => {
+                    if !stack_ok!(self, 2, "?do") {
+                        break;
+                    }
+                    let index = pop!(self);
+                    let limit = pop!(self);
+                    if index == limit {
+                        let offset = self.data[pc + 1];
+                        pc = (pc as i64 + 1).wrapping_add(offset) as usize; // skip the loop entirely
+                    } else if self.u_push_loop(limit, index, "?do") {
+                        pc += 2; // skip over the offset
+                    } else {
+                        break;
+                    }
+                }
+                LOOP | PLUS_LOOP => {
+                    let step = if code == LOOP {
+                        1
+                    } else if stack_ok!(self, 1, "+loop") {
+                        pop!(self)
+                    } else {
+                        break;
+                    };
+                    if !self.u_loop_frame_ok("loop") {
+                        break;
+                    }
+                    if self.u_loop_step(step) {
+                        self.return_ptr += 2; // discard the loop frame
+                        pc += 2; // skip over the offset
+                    } else {
+                        let offset = self.data[pc + 1];
+                        pc = (pc as i64 + 1).wrapping_add(offset) as usize;
+                        if self.get_abort_flag() {
+                            break;
                         }
+                    }
+                }
+                LEAVE => {
+                    if !self.u_loop_frame_ok("leave") {
+                        break;
+                    }
+                    self.return_ptr += 2;
+                    let offset = self.data[pc + 1];
+                    pc = (pc as i64 + 1).wrapping_add(offset) as usize;
+                }
+                DOES => {
+                    if !self.u_push_from(pc as i64 + 2, pc) {
+                        break; // no room for the address of the word's data
+                    }
+                    let code = self.data[pc + 1];
+                    if code == 0 {
+                        match self.u_return(base) {
+                            Some(addr) => pc = addr,
+                            None => break,
+                        }
+                    } else {
+                        pc = code as usize; // run the DOES> code, which returns to the caller
+                    }
+                }
+                LIT_PLUS | LIT_MINUS => {
+                    if !self.u_push_from(self.data[pc + 1], pc) {
+                        break;
+                    }
+                    if code == LIT_PLUS { self.f_plus() } else { self.f_minus() }; // these follow OVERFLOW-MODE
+                    if self.get_abort_flag() {
+                        break;
+                    }
+                    pc += 2;
+                }
+                DUP_BRANCH0 => {
+                    if !stack_ok!(self, 1, "dup") {
+                        break;
+                    }
+                    if top!(self) == 0 {
+                        let offset = self.data[pc + 1];
+                        pc = (pc as i64 + 1).wrapping_add(offset) as usize;
                     } else {
-                        pc += 1; // skip over the offset
+                        pc += 2;
+                    }
+                }
+                OVER_OVER => {
+                    if !stack_ok!(self, 2, "over") {
+                        break;
+                    }
+                    let (second, first) = (self.data[self.stack_ptr + 1], self.data[self.stack_ptr]);
+                    if !self.u_push_from(second, pc) || !self.u_push_from(first, pc) {
+                        break;
+                    }
+                    pc += 1;
+                }
+                R_FROM_DROP => {
+                    if self.return_ptr >= self.ret_start {
+                        self.u_error("r>", ERR_RETURN_UNDERFLOW, None::<bool>);
+                        break;
                     }
+                    self.return_ptr += 1;
+                    pc += 1;
                 }
                 ABORT => {
                     self.f_abort();
                     break;
                 }
-                EXIT => {
-                    // Current definition is finished, so pop the PC from the return stack
-                    self.f_r_from();
-                    pc = pop!(self) as usize;
-                }
-                BREAK => {
-                    // Breaks out of a word by popping the PC from the return stack
-                    self.f_r_from();
-                    pc = pop!(self) as usize;
+                EXIT | BREAK => {
+                    // Current definition is finished, or is being left early, so pop the PC from CALLS
+                    match self.u_return(base) {
+                        Some(addr) => pc = addr,
+                        None => break,
+                    }
                 }
                 _ => {
                     // we have a word address
                     // see if it's a builtin:
-                    let builtin_flag = code as usize & BUILTIN_MASK;
                     let address = code as usize & ADDRESS_MASK;
-                    if builtin_flag != 0 {
-                         push!(self, address as i64);
+                    if code as usize & BUILTIN_MASK != 0 {
+                        push!(self, address as i64);
                         self.i_builtin();
+                        if self.get_abort_flag() {
+                            break;
+                        }
+                        stepping = self.data[self.stepper_ptr] != 0;
                         pc += 1;
+                    } else if code as usize & TAIL_CALL_MASK != 0 {
+                        // nothing follows the call, so the word returns straight to our caller
+                        let colon = !stepping && self.data.get(address) == Some(&DEFINITION);
+                        pc = if colon { address + 1 } else { address };
+                    } else if self.calls.len() >= MAX_CALL_DEPTH {
+                        let name = self.u_word_name(address);
+                        self.u_error(&name, ERR_RETURN_OVERFLOW, None::<bool>);
+                        break;
                     } else {
-                        push!(self, pc as i64 + 1); // the return address is the next object in the list
-                        self.f_to_r(); // save it on the return stack
-                        pc = code as usize;
+                        self.calls.push(pc + 1); // the return address is the next object in the list
+                        // go straight to the body of a colon definition, unless the stepper wants to show its name
+                        let colon = !stepping && self.data.get(address) == Some(&DEFINITION);
+                        pc = if colon { address + 1 } else { address };
                     }
                 }
             }
         }
+        self.calls.truncate(base); // after an exception, CATCH or the outer interpreter will reset the stacks
+    }
+
+    /// u_return pops the return address of the calling definition, or returns None when the definition
+    ///     that i_definition started with has finished, or an exception is unwinding the calls
+    ///
+    fn u_return(&mut self, base: usize) -> Option<usize> {
+        if self.calls.len() > base && !self.get_abort_flag() {
+            self.calls.pop()
+        } else {
+            None
+        }
+    }
+
+    /// u_push_from pushes a value for the definition being run, naming the word at pc if the stack overflows
+    ///
+    fn u_push_from(&mut self, val: i64, pc: usize) -> bool {
+        if self.stack_ptr > self.data[self.here_ptr] as usize {
+            self.stack_ptr -= 1;
+            self.data[self.stack_ptr] = val;
+            true
+        } else {
+            let name = self.u_word_name(pc);
+            self.u_error(&name, ERR_STACK_OVERFLOW, None::<bool>);
+            false
+        }
+    }
+
+    /// u_word_name returns the name of the word whose definition holds addr
+    ///     The dictionary is walked back from the most recent word to the first one that starts below addr.
+    ///
+    pub fn u_word_name(&self, addr: usize) -> String {
+        let mut nfa = self.data[self.context_ptr] as usize;
+        while nfa > addr {
+            match self.data.get(nfa - 1) {
+                Some(&link) if link > 0 => nfa = link as usize + 1,
+                _ => break,
+            }
+        }
+        self.u_read_string(self.data[nfa] as usize).unwrap_or_default()
+    }
+
+    /// u_push_loop starts a counted loop by pushing its limit and index onto the return stack
+    ///     Returns false, throwing -5, if there isn't room.
+    ///
+    fn u_push_loop(&mut self, limit: i64, index: i64, caller: &str) -> bool {
+        if self.return_ptr < self.ret_end + 2 {
+            self.u_error(caller, ERR_RETURN_OVERFLOW, None::<bool>);
+            return false;
+        }
+        self.return_ptr -= 2;
+        self.data[self.return_ptr] = index;
+        self.data[self.return_ptr + 1] = limit;
+        true
+    }
+
+    /// u_loop_frame_ok checks that the return stack holds a loop frame, throwing -6 if not
+    ///
+    fn u_loop_frame_ok(&mut self, caller: &str) -> bool {
+        if self.return_ptr + 1 < self.ret_start {
+            true
+        } else {
+            self.u_error(caller, ERR_RETURN_UNDERFLOW, None::<bool>);
+            false
+        }
+    }
+
+    /// u_loop_step adds step to the loop index, returning true when the loop is finished
+    ///     As in standard Forth, the loop ends when the index crosses the boundary between limit - 1 and limit,
+    ///     in either direction. Wrapping arithmetic keeps this correct over the whole range of a cell.
+    ///
+    fn u_loop_step(&mut self, step: i64) -> bool {
+        let index = self.data[self.return_ptr];
+        let limit = self.data[self.return_ptr + 1];
+        let before = index.wrapping_sub(limit);
+        let after = before.wrapping_add(step);
+        self.data[self.return_ptr] = index.wrapping_add(step);
+        (before ^ after) < 0 && (before ^ step) < 0
     }
 
     /// Unconditional branch, used by condition and loop structures
@@ -199,11 +431,10 @@ impl TF {
     pub fn i_abort(&mut self) {}
 
     /// Leave the current word
-    ///     *** doesn't work, because there's no way to reset the program counter from here
+    ///     Executed on its own, EXIT has no definition to leave, so it just drops the address EXECUTE pushed
     ///
     pub fn i_exit(&mut self) {
-        self.f_r_from();
-        // pc = pop!(self) as usize;
+        pop!(self);
     }
 
 }
@@ -0,0 +1,78 @@
+// Stack and memory access macros shared by the builtin words
+//
+// Every Forth-visible access to DATA or STRINGS goes through these checks, so a bad address
+// or an empty stack aborts back to the prompt instead of panicking.
+
+/// stack_ok! checks that there are at least n values on the stack, aborting with an error if not
+macro_rules! stack_ok {
+    ($self:ident, $n: expr, $caller: expr) => {
+        if $self.stack_start.saturating_sub($self.stack_ptr) >= $n {
+            true
+        } else {
+            $self.u_error($caller, $crate::engine::ERR_STACK_UNDERFLOW, None::<bool>);
+            false
+        }
+    };
+}
+
+/// data_ok! checks that a cell address lies within the data area
+macro_rules! data_ok {
+    ($self:ident, $addr: expr, $caller: expr) => {
+        if ($addr as i64) >= $crate::engine::DATA_BASE as i64
+            && ($addr as i64) < ($crate::engine::DATA_BASE + $self.data_size) as i64
+        {
+            true
+        } else {
+            $self.u_invalid_address($caller, $addr as i64);
+            false
+        }
+    };
+}
+
+/// string_ok! checks that len characters starting at a string address lie within string space
+macro_rules! string_ok {
+    ($self:ident, $addr: expr, $len: expr, $caller: expr) => {
+        if ($addr as i64) >= 0
+            && ($addr as i64).saturating_add($len as i64) <= $self.string_size as i64
+        {
+            true
+        } else {
+            $self.u_invalid_address($caller, $addr as i64);
+            false
+        }
+    };
+}
+
+/// pop! removes the top of the stack. An empty stack aborts and yields 0.
+macro_rules! pop {
+    ($self:ident) => {{
+        if $self.stack_ptr < $self.stack_start {
+            let r = $self.data[$self.stack_ptr];
+            $self.stack_ptr += 1;
+            r
+        } else {
+            $self.u_stack_underflow();
+            0
+        }
+    }};
+}
+
+/// top! reads the top of the stack without removing it
+macro_rules! top {
+    ($self:ident) => {{
+        $self.data[$self.stack_ptr]
+    }};
+}
+
+/// push! places a value on the stack, unless the stack has grown down to the top of the dictionary
+macro_rules! push {
+    ($self:ident, $val:expr) => {
+        let val = $val;
+        if $self.stack_ptr > $self.data[$self.here_ptr] as usize {
+            $self.stack_ptr -= 1;
+            $self.data[$self.stack_ptr] = val;
+        } else {
+            $self.u_stack_overflow();
+        }
+    };
+}
@@ -0,0 +1,265 @@
+// Memory words
+//
+// Data space and string space share one address space. The address unit is the cell: string space holds one
+// character per address, from 0 up to the string size, and data space is mapped above it, starting at
+// DATA_BASE. A character is also a cell wide in data space, so CHARS and CELLS don't change an address, and
+// every address is aligned. C@ C! FILL MOVE and ERASE work in either space, while @ and ! only work on data space.
+//
+// String addresses are the same as they always were, so a string address written as a number, such as 264
+// for TMP, still works with every word. Data space is the one that moved. It is still an array of cells,
+// indexed by DataSpace, which takes a cell's address rather than its position in the array.
+//
+// Names and string literals are permanent: they are added at s-here, which only FORGET moves back, and
+// running out of room throws -256. Strings made by S" are transient. They go into a small ring that starts at
+// TMP_START, and TMP always points to the next free place in it. Each line of input starts again at the
+// beginning of the ring, so a transient string lasts until the end of its line, or until the ring wraps round.
+
+use crate::engine::{BUF_SIZE, DATA_BASE, ERR_DICTIONARY_OVERFLOW, ERR_STRING_SPACE, STACK_ROOM, TF, TMP_START};
+use crate::engine::TRANSIENT_SIZE;
+use std::ops::{Index, IndexMut, Range, RangeFrom};
+
+/// DataSpace holds the cells of data space, indexed by cell address
+///
+pub struct DataSpace(Vec<i64>);
+
+impl DataSpace {
+    pub fn new(size: usize) -> DataSpace {
+        DataSpace(vec![0; size])
+    }
+
+    /// offset converts a cell address into an index into the array
+    ///     Anything below DATA_BASE wraps round to an index that is out of range.
+    ///
+    pub fn offset(addr: usize) -> usize {
+        addr.wrapping_sub(DATA_BASE)
+    }
+
+    pub fn get(&self, addr: usize) -> Option<&i64> {
+        self.0.get(DataSpace::offset(addr))
+    }
+
+    /// copy_within copies the cells in src to dest, which may overlap
+    ///
+    pub fn copy_within(&mut self, src: Range<usize>, dest: usize) {
+        let start = DataSpace::offset(src.start);
+        self.0.copy_within(start..start + src.len(), DataSpace::offset(dest));
+    }
+
+    pub fn cells(&self) -> &[i64] {
+        &self.0
+    }
+
+    pub fn cells_mut(&mut self) -> &mut [i64] {
+        &mut self.0
+    }
+}
+
+impl Index<usize> for DataSpace {
+    type Output = i64;
+
+    fn index(&self, addr: usize) -> &i64 {
+        &self.0[DataSpace::offset(addr)]
+    }
+}
+
+impl IndexMut<usize> for DataSpace {
+    fn index_mut(&mut self, addr: usize) -> &mut i64 {
+        &mut self.0[DataSpace::offset(addr)]
+    }
+}
+
+impl Index<Range<usize>> for DataSpace {
+    type Output = [i64];
+
+    fn index(&self, range: Range<usize>) -> &[i64] {
+        let start = DataSpace::offset(range.start);
+        &self.0[start..start + range.len()]
+    }
+}
+
+impl Index<RangeFrom<usize>> for DataSpace {
+    type Output = [i64];
+
+    fn index(&self, range: RangeFrom<usize>) -> &[i64] {
+        &self.0[DataSpace::offset(range.start)..]
+    }
+}
+
+impl IndexMut<Range<usize>> for DataSpace {
+    fn index_mut(&mut self, range: Range<usize>) -> &mut [i64] {
+        let start = DataSpace::offset(range.start);
+        &mut self.0[start..start + range.len()]
+    }
+}
+
+impl TF {
+    /// allot ( n -- ) Reserves n cells of data space, or gives them back if n is negative
+    ///     Throws -8 if the dictionary would run into the stack, or drop below the most recent word.
+    ///
+    pub fn f_allot(&mut self) {
+        if stack_ok!(self, 1, "allot") {
+            let n = pop!(self);
+            let here = self.data[self.here_ptr];
+            let floor = self.data[self.context_ptr] + 2; // the code field of the newest word
+            match here.checked_add(n) {
+                Some(new) if new >= floor && new + (STACK_ROOM as i64) < self.stack_ptr as i64 => {
+                    self.data[self.here_ptr] = new;
+                }
+                _ => self.u_error("allot", ERR_DICTIONARY_OVERFLOW, Some(n)),
+            }
+        }
+    }
+
+    /// fill ( addr u c -- ) Stores character c in u consecutive addresses, starting at addr
+    ///
+    pub fn f_fill(&mut self) {
+        if stack_ok!(self, 3, "fill") {
+            let c = pop!(self) as u8 as i64;
+            let (addr, count) = self.u_pop_range();
+            if self.u_range_ok(addr, count, "fill") {
+                for a in addr..addr + count {
+                    self.u_store_unit(a, c);
+                }
+            }
+        }
+    }
+
+    /// erase ( addr u -- ) Stores zero in u consecutive addresses, starting at addr
+    ///
+    pub fn f_erase(&mut self) {
+        if stack_ok!(self, 2, "erase") {
+            let (addr, count) = self.u_pop_range();
+            if self.u_range_ok(addr, count, "erase") {
+                for a in addr..addr + count {
+                    self.u_store_unit(a, 0);
+                }
+            }
+        }
+    }
+
+    /// move ( from to u -- ) Copies the contents of u addresses, correctly even if the areas overlap
+    ///     Cells are copied whole within data space. Copying between the spaces copies characters.
+    ///
+    pub fn f_move(&mut self) {
+        if stack_ok!(self, 3, "move") {
+            let (to, count) = self.u_pop_range();
+            let from = pop!(self);
+            if self.u_range_ok(from, count, "move") && self.u_range_ok(to, count, "move") {
+                let units: Vec<i64> = (from..from + count).map(|a| self.u_fetch_unit(a)).collect();
+                for (a, unit) in (to..).zip(units) {
+                    self.u_store_unit(a, unit);
+                }
+            }
+        }
+    }
+
+    /// c@ ( addr -- c ) Fetches the character at addr, in string space or data space
+    ///
+    pub fn f_c_get(&mut self) {
+        if stack_ok!(self, 1, "c@") {
+            let addr = pop!(self);
+            if self.u_range_ok(addr, 1, "c@") {
+                push!(self, self.u_fetch_unit(addr) as u8 as i64);
+            }
+        }
+    }
+
+    /// c! ( c addr -- ) Stores character c at addr, in string space or data space
+    ///
+    pub fn f_c_store(&mut self) {
+        if stack_ok!(self, 2, "c!") {
+            let addr = pop!(self);
+            let c = pop!(self) as u8 as i64;
+            if self.u_range_ok(addr, 1, "c!") {
+                self.u_store_unit(addr, c);
+            }
+        }
+    }
+
+    /// s-transient ( s u -- s' u ) Copies a string of length u into the ring of transient strings, at TMP
+    ///     s is the counted string's address, as left by parse-to. TMP then moves past the string, so the next
+    ///     one doesn't overwrite it. s" parses straight into TMP, so the copy leaves its string where it is.
+    ///
+    pub fn f_s_transient(&mut self) {
+        if stack_ok!(self, 2, "s-transient") {
+            let length = pop!(self);
+            let source = pop!(self);
+            let length = match usize::try_from(length) {
+                Ok(n) if n < TRANSIENT_SIZE => n,
+                _ => {
+                    self.u_error("s-transient", ERR_STRING_SPACE, Some(length));
+                    return;
+                }
+            };
+            if !string_ok!(self, source, length + 1, "s-transient") {
+                return;
+            }
+            let ring = TMP_START..TMP_START + TRANSIENT_SIZE;
+            let mut dest = self.data[self.tmp_ptr] as usize;
+            if !ring.contains(&dest) || dest + length + 1 > ring.end {
+                dest = TMP_START; // wrap round to the start of the ring
+            }
+            self.strings.copy_within(source as usize..source as usize + length + 1, dest);
+            self.strings[dest] = length as u8 as char;
+            let mut next = dest + length + 1;
+            if next + BUF_SIZE > ring.end {
+                next = TMP_START; // leave room for a whole line to be parsed into TMP
+            }
+            self.data[self.tmp_ptr] = next as i64;
+            push!(self, dest as i64);
+            push!(self, length as i64);
+        }
+    }
+
+    /// u_string_alloc reserves room at s-here for a counted string of length characters, returning its address
+    ///     Throws -256 if string space is exhausted.
+    ///
+    pub fn u_string_alloc(&mut self, length: usize, caller: &str) -> Option<usize> {
+        let addr = self.data[self.string_ptr] as usize;
+        if addr + length + 1 > self.string_size {
+            self.u_error(caller, ERR_STRING_SPACE, Some(length));
+            return None;
+        }
+        self.data[self.string_ptr] += length as i64 + 1;
+        Some(addr)
+    }
+
+    /// u_pop_range pops a count, then an address
+    ///     A negative count is treated as zero, so nothing is changed.
+    ///
+    fn u_pop_range(&mut self) -> (i64, i64) {
+        let count = pop!(self).max(0);
+        let addr = pop!(self);
+        (addr, count)
+    }
+
+    /// u_range_ok checks that count addresses from addr lie within data space or within string space
+    ///
+    fn u_range_ok(&mut self, addr: i64, count: i64, caller: &str) -> bool {
+        if addr >= DATA_BASE as i64 {
+            data_ok!(self, addr, caller) && (count == 0 || data_ok!(self, addr.saturating_add(count - 1), caller))
+        } else {
+            string_ok!(self, addr, count, caller)
+        }
+    }
+
+    /// u_fetch_unit reads the cell or character at an address already checked by u_range_ok
+    ///
+    fn u_fetch_unit(&self, addr: i64) -> i64 {
+        if addr >= DATA_BASE as i64 {
+            self.data[addr as usize]
+        } else {
+            self.strings[addr as usize] as i64
+        }
+    }
+
+    /// u_store_unit writes a cell, or a character to string space, at an address already checked by u_range_ok
+    ///
+    fn u_store_unit(&mut self, addr: i64, value: i64) {
+        if addr >= DATA_BASE as i64 {
+            self.data[addr as usize] = value;
+        } else {
+            self.strings[addr as usize] = char::from_u32(value as u32).unwrap_or(' ');
+        }
+    }
+}
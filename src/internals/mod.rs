@@ -1,6 +1,19 @@
+#[macro_use]
+mod macros;
+
 pub mod builtin;
 pub mod compiler;
 pub mod console;
 pub mod debug;
+pub mod doubles;
+pub mod exceptions;
+pub mod floats;
 pub mod general;
+pub mod heap;
+pub mod image;
+pub mod index;
 pub mod inner;
+pub mod memory;
+pub mod optimizer;
+pub mod pictured;
+pub mod wordlists;
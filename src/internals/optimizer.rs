@@ -0,0 +1,197 @@
+// Peephole optimizer
+//
+// When ; finishes a definition, its code is decoded into a list of instructions, improved, and written back
+// in place. Jumps to unconditional branches are sent straight to the final destination, and a branch to EXIT
+// becomes an early exit. Common pairs of words are then fused into superinstructions, which the inner interpreter
+// runs with one dispatch instead of two. Pairs are never fused when a branch lands between them.
+// Finally, a call followed by EXIT is marked as a tail call, so it reuses the caller's return address.
+// The code only ever gets shorter, so the definition is rewritten where it is, and branch offsets are recomputed.
+//
+// A definition is left alone if it holds anything the optimizer doesn't understand, such as data compiled
+// with , or the absolute code address compiled by DOES>. A cell is only taken to be a call if it is the code
+// field of a word in the dictionary, or the compiled form of a builtin.
+
+use crate::engine::{
+    ABORT, ADDRESS_MASK, BRANCH, BRANCH0, BREAK, BUILTIN_MASK, DO, DUP_BRANCH0, EXIT, FLITERAL, LEAVE, LITERAL,
+    LIT_MINUS, LIT_PLUS, LOOP, OVER_OVER, PLUS_LOOP, QDO, R_FROM_DROP, STRLIT, TAIL_CALL_MASK, TF, WORD_START,
+};
+use std::collections::HashMap;
+
+/// One decoded instruction. For a branch, arg holds the address of its target rather than the offset.
+///
+#[derive(Clone, Copy)]
+struct Instr {
+    addr: usize,
+    code: i64,
+    arg: Option<i64>,
+}
+
+/// is_branch is true for opcodes whose argument is a branch offset
+///
+fn is_branch(code: i64) -> bool {
+    matches!(code, BRANCH | BRANCH0 | DUP_BRANCH0 | QDO | LOOP | PLUS_LOOP | LEAVE)
+}
+
+impl TF {
+    /// u_optimize improves the compiled code from start up to end, returning the new end
+    ///
+    pub fn u_optimize(&mut self, start: usize, end: usize) -> usize {
+        let Some(mut code) = self.u_decode(start, end) else {
+            return end; // not something the optimizer understands
+        };
+        u_thread_jumps(&mut code);
+        let mut code = self.u_fuse(code);
+        u_mark_tail_calls(&mut code);
+        self.u_encode(start, &code)
+    }
+
+    /// u_decode splits the code into instructions, checking that every branch lands on one
+    ///
+    fn u_decode(&self, start: usize, end: usize) -> Option<Vec<Instr>> {
+        let does = self.u_builtin_code("(does>)");
+        let mut index = self.index.borrow_mut();
+        index.sync(&self.data, &self.strings, self.data[self.context_ptr] as usize);
+        let recurse = self.data[self.last_ptr] + 1; // the word being defined isn't in the index yet
+        let is_word = |op: i64| op == recurse || index.is_cfa(op as usize);
+        let is_builtin = |op: i64| op as usize & BUILTIN_MASK != 0 && op as usize & ADDRESS_MASK < self.builtins.len();
+        let mut code = Vec::new();
+        let mut addr = start;
+        while addr < end {
+            let op = self.data[addr];
+            let instr = match op {
+                LITERAL | STRLIT | FLITERAL => Instr { addr, code: op, arg: Some(*self.data.get(addr + 1)?) },
+                _ if is_branch(op) => {
+                    let offset = *self.data.get(addr + 1)?;
+                    Instr { addr, code: op, arg: Some((addr as i64 + 1).wrapping_add(offset)) }
+                }
+                DO | ABORT | EXIT | BREAK => Instr { addr, code: op, arg: None },
+                _ if op == does => return None, // the DOES> code is found by its address
+                _ if is_builtin(op) || is_word(op) => Instr { addr, code: op, arg: None },
+                _ => return None, // an opcode that doesn't belong in a definition: probably data
+            };
+            addr += if instr.arg.is_some() { 2 } else { 1 };
+            code.push(instr);
+        }
+        let starts: Vec<usize> = code.iter().map(|i| i.addr).collect();
+        let lands = |target: i64| starts.binary_search(&(target as usize)).is_ok() || target as usize == end;
+        if addr != end || code.iter().any(|i| is_branch(i.code) && !lands(i.arg.unwrap_or(-1))) {
+            return None;
+        }
+        Some(code)
+    }
+
+    /// u_fuse replaces pairs of instructions with superinstructions, unless a branch lands on the second
+    ///
+    fn u_fuse(&self, code: Vec<Instr>) -> Vec<Instr> {
+        let plus = self.u_builtin_code("+");
+        let minus = self.u_builtin_code("-");
+        let dup = self.u_builtin_code("dup");
+        let over = self.u_builtin_code("over");
+        let r_from = self.u_builtin_code("r>");
+        let drop = self.u_builtin_code("drop");
+        let targets: Vec<i64> = code.iter().filter(|i| is_branch(i.code)).filter_map(|i| i.arg).collect();
+        let mut fused = Vec::with_capacity(code.len());
+        let mut i = 0;
+        while i < code.len() {
+            let first = code[i];
+            let second = code.get(i + 1).filter(|next| !targets.contains(&(next.addr as i64)));
+            let pair = second.and_then(|second| match (first.code, second.code) {
+                (LITERAL, op) if op == plus => Some(Instr { code: LIT_PLUS, ..first }),
+                (LITERAL, op) if op == minus => Some(Instr { code: LIT_MINUS, ..first }),
+                (op, BRANCH0) if op == dup => Some(Instr { code: DUP_BRANCH0, arg: second.arg, ..first }),
+                (op1, op2) if op1 == over && op2 == over => Some(Instr { code: OVER_OVER, ..first }),
+                (op1, op2) if op1 == r_from && op2 == drop => Some(Instr { code: R_FROM_DROP, ..first }),
+                _ => None,
+            });
+            match pair {
+                Some(instr) => {
+                    fused.push(instr);
+                    i += 2;
+                }
+                None => {
+                    fused.push(first);
+                    i += 1;
+                }
+            }
+        }
+        fused
+    }
+
+    /// u_encode writes the instructions back from start, converting branch targets to offsets
+    ///     Returns the address after the last instruction.
+    ///
+    fn u_encode(&mut self, start: usize, code: &[Instr]) -> usize {
+        let mut moved = HashMap::new(); // old address of each instruction -> new address
+        let mut addr = start;
+        for instr in code {
+            moved.insert(instr.addr as i64, addr as i64);
+            addr += if instr.arg.is_some() { 2 } else { 1 };
+        }
+        let end = addr;
+        let mut addr = start;
+        for instr in code {
+            self.data[addr] = instr.code;
+            if let Some(arg) = instr.arg {
+                self.data[addr + 1] = if is_branch(instr.code) {
+                    let target = moved.get(&arg).copied().unwrap_or(end as i64); // only the end isn't an instruction
+                    target - (addr as i64 + 1)
+                } else {
+                    arg
+                };
+                addr += 2;
+            } else {
+                addr += 1;
+            }
+        }
+        end
+    }
+
+    /// u_builtin_code returns the compiled form of the builtin with the given name, or 0 if there isn't one
+    ///
+    fn u_builtin_code(&self, name: &str) -> i64 {
+        match self.builtins.iter().position(|b| b.name == name) {
+            Some(index) => (index | BUILTIN_MASK) as i64,
+            None => 0,
+        }
+    }
+}
+
+/// u_thread_jumps sends branches that land on an unconditional branch to its destination,
+///     and turns an unconditional branch to EXIT into BREAK, so SEE still finds the end of the definition
+///
+fn u_thread_jumps(code: &mut [Instr]) {
+    let index: HashMap<i64, usize> = code.iter().enumerate().map(|(n, i)| (i.addr as i64, n)).collect();
+    for n in 0..code.len() {
+        if !matches!(code[n].code, BRANCH | BRANCH0) {
+            continue;
+        }
+        let mut target = code[n].arg.unwrap_or_default();
+        for _ in 0..code.len() {
+            // a chain can't be longer than the code, unless it loops forever
+            match index.get(&target).map(|&t| code[t]) {
+                Some(next) if next.code == BRANCH && next.arg != Some(target) => target = next.arg.unwrap_or_default(),
+                _ => break,
+            }
+        }
+        code[n].arg = Some(target);
+        let lands_on_exit = index.get(&target).is_some_and(|&t| matches!(code[t].code, EXIT | BREAK));
+        if code[n].code == BRANCH && lands_on_exit {
+            code[n] = Instr { code: BREAK, arg: None, ..code[n] };
+        }
+    }
+}
+
+/// u_mark_tail_calls flags calls to other definitions that are followed by EXIT, or by an early exit
+///     A tail call jumps to the word without saving a return address, so when the word finishes it returns
+///     straight to our caller. Recursion in tail position then runs in constant space.
+///     The EXIT is left in place, because a branch may still land on it.
+///
+fn u_mark_tail_calls(code: &mut [Instr]) {
+    for n in 1..code.len() {
+        let call = code[n - 1].code;
+        let is_call = call >= WORD_START as i64 && call as usize & BUILTIN_MASK == 0;
+        if is_call && matches!(code[n].code, EXIT | BREAK) {
+            code[n - 1].code = (call as usize | TAIL_CALL_MASK) as i64;
+        }
+    }
+}
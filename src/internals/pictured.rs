@@ -0,0 +1,115 @@
+// Pictured numeric output
+//
+// <# starts a number string in the hold buffer, a fixed area of string space. The string is built from the
+// right, one character at a time, so digits are produced least significant first. HLD holds the address of
+// the leftmost character so far. #> drops the number and leaves the address and length of the string,
+// ready for TELL. The number being converted is an unsigned double, so every cell value can be printed.
+
+use crate::engine::{ERR_HOLD_OVERFLOW, ERR_INVALID_ARGUMENT, HOLD_END, HOLD_START, TF};
+
+impl TF {
+    /// <# ( -- ) starts a pictured numeric output string, emptying the hold buffer
+    ///
+    pub fn f_less_number(&mut self) {
+        self.data[self.hld_ptr] = HOLD_END as i64;
+    }
+
+    /// hold ( c -- ) adds a character to the left of the pictured numeric output string
+    ///     Throws -24 if c is not a valid character code.
+    ///
+    pub fn f_hold(&mut self) {
+        if stack_ok!(self, 1, "hold") {
+            let c = pop!(self);
+            match u32::try_from(c).ok().and_then(char::from_u32) {
+                Some(c) => self.u_hold(c, "hold"),
+                None => self.u_error("hold", ERR_INVALID_ARGUMENT, Some(c)),
+            }
+        }
+    }
+
+    /// holds ( s u -- ) adds a string to the left of the pictured numeric output string
+    ///
+    pub fn f_holds(&mut self) {
+        if stack_ok!(self, 2, "holds") {
+            let len = pop!(self);
+            let addr = pop!(self);
+            if string_ok!(self, addr, len, "holds") {
+                for i in (addr..addr + len).rev() {
+                    self.u_hold(self.strings[i as usize], "holds");
+                }
+            }
+        }
+    }
+
+    /// sign ( n -- ) adds a minus sign to the pictured numeric output string if n is negative
+    ///
+    pub fn f_sign(&mut self) {
+        if stack_ok!(self, 1, "sign") && pop!(self) < 0 {
+            self.u_hold('-', "sign");
+        }
+    }
+
+    /// # ( ud1 -- ud2 ) divides ud1 by BASE, adding the remainder to the string as a digit
+    ///
+    pub fn f_number_sign(&mut self) {
+        if stack_ok!(self, 2, "#") {
+            let ud = self.u_pop_double() as u128;
+            if let Some(quot) = self.u_hold_digit(ud, "#") {
+                self.u_push_double(quot as i128);
+            }
+        }
+    }
+
+    /// #s ( ud -- 0 0 ) adds digits to the string until the number is zero. There is always at least one digit.
+    ///
+    pub fn f_number_sign_s(&mut self) {
+        if stack_ok!(self, 2, "#s") {
+            let mut ud = self.u_pop_double() as u128;
+            loop {
+                match self.u_hold_digit(ud, "#s") {
+                    Some(0) => break,
+                    Some(quot) => ud = quot,
+                    None => return,
+                }
+            }
+            self.u_push_double(0);
+        }
+    }
+
+    /// #> ( xd -- s u ) ends the pictured numeric output string, leaving its address and length
+    ///
+    pub fn f_number_greater(&mut self) {
+        if stack_ok!(self, 2, "#>") {
+            self.u_pop_double();
+            let hld = self.data[self.hld_ptr];
+            push!(self, hld);
+            push!(self, HOLD_END as i64 - hld);
+        }
+    }
+
+    /// u_hold_digit adds the last digit of ud in the current base to the string, returning the rest of ud
+    ///     Throws -24 if BASE is not between 2 and 36.
+    ///
+    fn u_hold_digit(&mut self, ud: u128, caller: &str) -> Option<u128> {
+        let base = self.data[self.base_ptr];
+        if !(2..=36).contains(&base) {
+            self.u_error(caller, ERR_INVALID_ARGUMENT, Some(base));
+            return None;
+        }
+        let digit = char::from_digit((ud % base as u128) as u32, base as u32).unwrap_or('?');
+        self.u_hold(digit.to_ascii_uppercase(), caller);
+        Some(ud / base as u128)
+    }
+
+    /// u_hold adds a character to the left of the string, throwing -17 if the hold buffer is full
+    ///
+    fn u_hold(&mut self, c: char, caller: &str) {
+        let hld = self.data[self.hld_ptr] as usize;
+        if hld > HOLD_START && hld <= HOLD_END {
+            self.data[self.hld_ptr] -= 1;
+            self.strings[hld - 1] = c;
+        } else {
+            self.u_error(caller, ERR_HOLD_OVERFLOW, None::<bool>);
+        }
+    }
+}
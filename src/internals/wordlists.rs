@@ -0,0 +1,178 @@
+// Wordlists and the search order
+//
+// The dictionary is still a single linked list. Each name field records the wordlist its word belongs to,
+// in the WORDLIST_MASK bits, and FIND only accepts words from wordlists in the search order.
+// Lookup goes through the hashed index in index.rs, which is kept in step with the linked list.
+// The search order and the current (compilation) wordlist are kept in data space, so they are saved in images.
+
+use crate::engine::{
+    ERR_INVALID_ARGUMENT, ERR_ORDER_OVERFLOW, ERR_ORDER_UNDERFLOW, ERR_UNSUPPORTED, FORTH_WORDLIST, MAX_ORDER,
+    MAX_WORDLISTS, TF, WORDLIST_SHIFT,
+};
+
+impl TF {
+    /// wordlist ( -- wid ) creates a new, empty wordlist
+    ///
+    pub fn f_wordlist(&mut self) {
+        let wid = self.data[self.wordlists_ptr];
+        if wid as usize >= MAX_WORDLISTS {
+            self.u_error("wordlist", ERR_UNSUPPORTED, Some("too many wordlists"));
+        } else {
+            self.data[self.wordlists_ptr] += 1;
+            push!(self, wid);
+        }
+    }
+
+    /// forth-wordlist ( -- wid ) the wordlist holding the builtins and the core library
+    ///
+    pub fn f_forth_wordlist(&mut self) {
+        push!(self, FORTH_WORDLIST);
+    }
+
+    /// get-order ( -- widn .. wid1 n ) pushes the search order, with the first wordlist searched on top
+    ///
+    pub fn f_get_order(&mut self) {
+        let n = self.data[self.order_ptr] as usize;
+        for i in (0..n).rev() {
+            push!(self, self.data[self.order_ptr + 1 + i]);
+        }
+        push!(self, n as i64);
+    }
+
+    /// set-order ( widn .. wid1 n -- ) sets the search order, searching wid1 first
+    ///     If n is -1, the search order is set to the minimum, as ONLY does.
+    ///
+    pub fn f_set_order(&mut self) {
+        if stack_ok!(self, 1, "set-order") {
+            let n = pop!(self);
+            if n == -1 {
+                self.f_only();
+                return;
+            }
+            if n < -1 {
+                self.u_error("set-order", ERR_INVALID_ARGUMENT, Some(n));
+                return;
+            }
+            if n as usize > MAX_ORDER {
+                self.u_error("set-order", ERR_ORDER_OVERFLOW, Some(n));
+                return;
+            }
+            let n = n as usize;
+            if !stack_ok!(self, n, "set-order") {
+                return;
+            }
+            let mut order = [FORTH_WORDLIST; MAX_ORDER];
+            for wid in order.iter_mut().take(n) {
+                *wid = pop!(self);
+                if !self.u_wordlist_ok(*wid, "set-order") {
+                    return;
+                }
+            }
+            self.data[self.order_ptr] = n as i64;
+            self.data[self.order_ptr + 1..self.order_ptr + 1 + MAX_ORDER].copy_from_slice(&order);
+        }
+    }
+
+    /// get-current ( -- wid ) pushes the wordlist new definitions are added to
+    ///
+    pub fn f_get_current(&mut self) {
+        push!(self, self.data[self.current_ptr]);
+    }
+
+    /// set-current ( wid -- ) makes new definitions go into wordlist wid
+    ///
+    pub fn f_set_current(&mut self) {
+        if stack_ok!(self, 1, "set-current") {
+            let wid = pop!(self);
+            if self.u_wordlist_ok(wid, "set-current") {
+                self.data[self.current_ptr] = wid;
+            }
+        }
+    }
+
+    /// only ( -- ) sets the search order to just the forth wordlist
+    ///
+    pub fn f_only(&mut self) {
+        self.data[self.order_ptr] = 1;
+        self.data[self.order_ptr + 1] = FORTH_WORDLIST;
+    }
+
+    /// also ( -- ) duplicates the first wordlist in the search order
+    ///     Usually followed by a vocabulary name, which replaces the copy.
+    ///
+    pub fn f_also(&mut self) {
+        let n = self.data[self.order_ptr] as usize;
+        if n == 0 {
+            self.u_error("also", ERR_ORDER_UNDERFLOW, None::<bool>);
+        } else if n == MAX_ORDER {
+            self.u_error("also", ERR_ORDER_OVERFLOW, None::<bool>);
+        } else {
+            let first = self.order_ptr + 1;
+            self.data.copy_within(first..first + n, first + 1);
+            self.data[self.order_ptr] += 1;
+        }
+    }
+
+    /// previous ( -- ) removes the first wordlist from the search order
+    ///     The last wordlist isn't removed: with nothing to search, not even ONLY could be found.
+    ///
+    pub fn f_previous(&mut self) {
+        let n = self.data[self.order_ptr] as usize;
+        if n <= 1 {
+            self.u_error("previous", ERR_ORDER_UNDERFLOW, None::<bool>);
+        } else {
+            let first = self.order_ptr + 1;
+            self.data.copy_within(first + 1..first + n, first);
+            self.data[self.order_ptr] -= 1;
+        }
+    }
+
+    /// definitions ( -- ) makes new definitions go into the first wordlist in the search order
+    ///
+    pub fn f_definitions(&mut self) {
+        if self.data[self.order_ptr] == 0 {
+            self.u_error("definitions", ERR_ORDER_UNDERFLOW, None::<bool>);
+        } else {
+            self.data[self.current_ptr] = self.data[self.order_ptr + 1];
+        }
+    }
+
+    /// order ( -- ) prints the search order, first searched first, and the current wordlist
+    ///
+    pub fn f_order(&mut self) {
+        let n = self.data[self.order_ptr] as usize;
+        let order: Vec<String> = self.data[self.order_ptr + 1..self.order_ptr + 1 + n]
+            .iter()
+            .map(|wid| wid.to_string())
+            .collect();
+        println!("{} ( current: {} )", order.join(" "), self.data[self.current_ptr]);
+    }
+
+    /// u_wordlist_ok checks that wid is a wordlist that has been created, throwing -24 if not
+    ///
+    fn u_wordlist_ok(&mut self, wid: i64, caller: &str) -> bool {
+        if (0..self.data[self.wordlists_ptr]).contains(&wid) {
+            true
+        } else {
+            self.u_error(caller, ERR_INVALID_ARGUMENT, Some(wid));
+            false
+        }
+    }
+
+    /// u_current_wordlist returns the name field flags that put a new word in the current wordlist
+    ///
+    pub fn u_current_wordlist(&self) -> i64 {
+        ((self.data[self.current_ptr] as usize) << WORDLIST_SHIFT) as i64
+    }
+
+    /// u_search finds the most recent word with this name, in the first wordlist of the search order
+    ///     that has one. Returns the word's cfa.
+    ///
+    pub fn u_search(&self, name: &str) -> Option<usize> {
+        let n = (self.data[self.order_ptr] as usize).min(MAX_ORDER);
+        let order = &self.data[self.order_ptr + 1..self.order_ptr + 1 + n];
+        let mut index = self.index.borrow_mut();
+        index.sync(&self.data, &self.strings, self.data[self.context_ptr] as usize);
+        index.find(name, order)
+    }
+}
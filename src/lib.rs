@@ -0,0 +1,15 @@
+// f2 library
+//
+// Exposes the Forth engine so that it can be embedded in other Rust programs.
+// The binary in main.rs is a thin wrapper around Config::run_forth.
+
+pub mod api;
+pub mod config;
+pub mod engine;
+pub mod files;
+pub mod internals;
+pub mod messages;
+pub mod utility;
+
+pub use api::ForthError;
+pub use engine::TF;
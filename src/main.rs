@@ -1,14 +1,7 @@
 // f2 main program
 // Version 0.1
 
-mod config;
-mod engine;
-mod messages;
-mod files;
-//mod tokenizer;
-mod internals;
-
-use config::Config;
+use f2::config::Config;
 
 fn main() {
     let mut config = Config::new();
@@ -15,6 +15,12 @@ pub struct Msg {
     debug_level: DebugLevel,
 }
 
+impl Default for Msg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// A simple message processing system that allows the user to set message levels
 ///
 impl Msg {
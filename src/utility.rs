@@ -8,6 +8,85 @@ pub fn is_float(s: &str) -> bool {
     s.parse::<f64>().is_ok()
 }
 
+/// The result of reading a token as an integer
+#[derive(Debug, PartialEq)]
+pub enum Number {
+    Single(i64),
+    Double(i128),
+    Overflow, // the digits are valid, but there are too many of them
+    Invalid,
+}
+
+/// parse_number reads an integer in the given base (2 to 36)
+///     A prefix overrides the base: # for decimal, $ for hex and % for binary. It may be followed by a minus sign.
+///     A trailing decimal point makes the number a double. A character in single quotes, such as 'a', gives
+///     its character code. As cells are used for unsigned numbers too, a single may go up to u64::MAX,
+///     which wraps to a negative number, and a double up to u128::MAX.
+///
+pub fn parse_number(s: &str, base: u32) -> Number {
+    let chars: Vec<char> = s.chars().collect();
+    if let ['\'', c, '\''] = chars[..] {
+        return Number::Single(c as i64);
+    }
+    let (base, s) = match s.chars().next() {
+        Some('#') => (10, &s[1..]),
+        Some('$') => (16, &s[1..]),
+        Some('%') => (2, &s[1..]),
+        _ => (base, s),
+    };
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (double, digits) = match s.strip_suffix('.') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(base)) {
+        return Number::Invalid;
+    }
+    let mut value: u128 = 0;
+    for c in digits.chars() {
+        let digit = c.to_digit(base).unwrap_or_default() as u128;
+        match value.checked_mul(base as u128).and_then(|v| v.checked_add(digit)) {
+            Some(v) => value = v,
+            None => return Number::Overflow,
+        }
+    }
+    if double {
+        let d = value as i128;
+        Number::Double(if negative { d.wrapping_neg() } else { d })
+    } else if value > u64::MAX as u128 {
+        Number::Overflow
+    } else {
+        let n = value as u64 as i64;
+        Number::Single(if negative { n.wrapping_neg() } else { n })
+    }
+}
+
+/// float_literal converts a Forth float literal, such as 1.5e0, -2e3 or 1E, to a float
+///     The exponent marker is required, so that 123 and 123. are left to be read as integers.
+///     An empty exponent means zero.
+///
+pub fn float_literal(s: &str) -> Option<f64> {
+    let (mantissa, exponent) = s.split_once(['e', 'E'])?;
+    if !mantissa.chars().any(|c| c.is_ascii_digit())
+        || !mantissa.chars().all(|c| c.is_ascii_digit() || "+-.".contains(c))
+    {
+        return None; // rules out inf, nan and the like
+    }
+    let exponent = match exponent {
+        "" | "+" | "-" => "0",
+        _ => exponent,
+    };
+    let text = format!("{mantissa}e{exponent}");
+    if is_float(&text) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -53,4 +132,70 @@ mod tests {
     fn is_float5() {
         assert!(!is_float("blah"));
     }
+
+    #[test]
+    fn float_literal1() {
+        assert_eq!(float_literal("1.5e0"), Some(1.5));
+    }
+    #[test]
+    fn float_literal2() {
+        assert_eq!(float_literal("-25E-1"), Some(-2.5));
+    }
+    #[test]
+    fn float_literal3() {
+        assert_eq!(float_literal("1e"), Some(1.0));
+    }
+    #[test]
+    fn float_literal4() {
+        assert_eq!(float_literal("1.5"), None);
+    }
+    #[test]
+    fn float_literal5() {
+        assert_eq!(float_literal("e5"), None);
+    }
+    #[test]
+    fn float_literal6() {
+        assert_eq!(float_literal("infe0"), None);
+    }
+
+    #[test]
+    fn parse_number1() {
+        assert_eq!(parse_number("-42", 10), Number::Single(-42));
+    }
+    #[test]
+    fn parse_number2() {
+        assert_eq!(parse_number("ff", 16), Number::Single(255));
+    }
+    #[test]
+    fn parse_number3() {
+        assert_eq!(parse_number("$-1F", 10), Number::Single(-31));
+    }
+    #[test]
+    fn parse_number4() {
+        assert_eq!(parse_number("%101.", 16), Number::Double(5));
+    }
+    #[test]
+    fn parse_number5() {
+        assert_eq!(parse_number("#19", 2), Number::Single(19));
+    }
+    #[test]
+    fn parse_number6() {
+        assert_eq!(parse_number("'a'", 10), Number::Single(97));
+    }
+    #[test]
+    fn parse_number7() {
+        assert_eq!(parse_number("$FFFFFFFFFFFFFFFF", 10), Number::Single(-1));
+    }
+    #[test]
+    fn parse_number8() {
+        assert_eq!(parse_number("18446744073709551616", 10), Number::Overflow);
+    }
+    #[test]
+    fn parse_number9() {
+        assert_eq!(parse_number("12", 2), Number::Invalid);
+    }
+    #[test]
+    fn parse_number10() {
+        assert_eq!(parse_number("-", 10), Number::Invalid);
+    }
 }